@@ -5,7 +5,31 @@ use biggun_lib::prelude::*;
 const BG_COLOR: Color = Color::srgb(0.01, 0.01, 0.01);
 
 fn main() {
-    App::new()
+    let config = match arg_value("--config") {
+        Some(path) => Config::from_file(&path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }),
+        None => Config::default(),
+    };
+    let replay = arg_value("--replay").map(|path| {
+        Replay::from_file(&path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        })
+    });
+    let rng = match (&replay, arg_value("--seed")) {
+        // Replays always use the seed they were recorded with
+        (Some(replay), _) => GameRng::from_seed(replay.seed()),
+        (None, Some(seed)) => GameRng::from_seed(seed.parse().unwrap_or_else(|e| {
+            eprintln!("--seed {seed}: {e}");
+            std::process::exit(1);
+        })),
+        (None, None) => GameRng::default(),
+    };
+
+    let mut app = App::new();
+    app
         // Official bevy plugins
         .add_plugins(DefaultPlugins.set(
             ImagePlugin::default_nearest(), // Use pixel perfect sprites
@@ -21,6 +45,15 @@ fn main() {
         ))
         .insert_resource(ClearColor(BG_COLOR))
         .insert_resource(GameState::default())
-        .insert_resource(Config::default())
-        .run();
+        .insert_resource(config)
+        .insert_resource(rng);
+    if let Some(replay) = replay {
+        // Played with the recorded players, whatever is picked on the menu
+        app.insert_resource(replay.session())
+            .insert_resource(Playback::new(replay));
+    }
+    if let Some(path) = arg_value("--record") {
+        app.insert_resource(Recorder::new(path));
+    }
+    app.run();
 }
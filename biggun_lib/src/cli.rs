@@ -0,0 +1,19 @@
+//! Command line handling shared by the game and the sim
+
+/// Reads the value given for `flag` as `<flag> <value>` or `<flag>=<value>`,
+/// e.g. `cargo run -- --config biggun_game/assets/config.ron --seed 42`
+pub fn arg_value(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next();
+        }
+        if let Some(value) = arg
+            .strip_prefix(flag)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
@@ -0,0 +1,246 @@
+//! What free fish do about lures. Each fish cruises along until it sees a
+//! lure ahead of it, watches it for a moment, then goes for it or ignores it.
+//! Fish going for a lure swim up to it and strike once close enough, and only
+//! a striking fish bites. Lures given a few short twitches of reel or steer
+//! are more tempting, while jerking one about scares off every fish watching
+//! it.
+
+use crate::{
+    environment::{
+        fish::{Fish, HookedBy},
+        obstacle::Sheltered,
+        species::{Species, SpeciesLibrary},
+    },
+    game_manager::{config::Config, rng::GameRng},
+    player::{
+        hook::Hook,
+        lure::{Lure, LureConfig, LureMotion},
+    },
+};
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+/// How a species reacts to lures
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Temperament {
+    /// How far the species sees lures, relative to their
+    /// `attraction_radius`
+    pub sight: f32,
+    /// Seconds spent watching a lure before deciding on it
+    pub notice_time: f32,
+    /// Added to a lure's appeal if it was twitched while being watched
+    pub twitch_appeal: f32,
+    /// How many presses a second, as counted by `LureMotion::jerk`, scare
+    /// the fish off
+    pub wariness: f32,
+    /// How close the mouth must get to a lure before the fish strikes
+    pub strike_range: f32,
+    /// How many times faster than usual a striking fish swims
+    pub strike_speed: f32,
+}
+
+impl Default for Temperament {
+    fn default() -> Self {
+        Self {
+            sight: 1.,
+            notice_time: 1.5,
+            twitch_appeal: 0.3,
+            wariness: 3.,
+            strike_range: 16.,
+            strike_speed: 3.,
+        }
+    }
+}
+
+/// What a free fish is doing. The hook is the one whose lure the fish has
+/// its eye on.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum Behaviour {
+    /// Swimming along without a lure in sight
+    #[default]
+    Cruise,
+    /// Slowed down to watch a lure for `secs` more seconds. `twitched` is
+    /// set once the lure has been twitched meanwhile.
+    Notice {
+        hook: Entity,
+        secs: f32,
+        twitched: bool,
+    },
+    /// Swimming up to a lure it decided to go for
+    Approach { hook: Entity },
+    /// Darting at a lure within reach. Bites once its mouth touches it.
+    Strike { hook: Entity },
+    /// Carrying on past a lure it decided against
+    Ignore { hook: Entity },
+    /// Fleeing for `secs` more seconds after a bump or a jerked lure. Won't
+    /// bite or notice lures meanwhile.
+    Spook { secs: f32 },
+    /// A predator going after the fish struggling on a hook. See
+    /// `predator::hunt`.
+    Hunt { hook: Entity },
+    /// A predator going after a free schooling fish, last seen at `at`. See
+    /// `predator::hunt`.
+    Chase { prey: Entity, at: Vec2 },
+}
+
+impl Behaviour {
+    /// Fraction of its speed a fish keeps while watching a lure
+    pub const NOTICE_SPEED: f32 = 0.3;
+    /// How quickly fish close the vertical gap to a lure they are watching or
+    /// going for, per second
+    pub const HOMING: f32 = 3.;
+
+    /// The hook whose lure the fish has its eye on
+    pub fn hook(&self) -> Option<Entity> {
+        match *self {
+            Self::Notice { hook, .. }
+            | Self::Approach { hook }
+            | Self::Strike { hook }
+            | Self::Ignore { hook }
+            | Self::Hunt { hook } => Some(hook),
+            Self::Cruise | Self::Spook { .. } | Self::Chase { .. } => None,
+        }
+    }
+}
+
+/// Decides whether a fish done watching `lure` goes for it
+fn decide(
+    hook: Entity,
+    lure: &LureConfig,
+    twitched: bool,
+    temperament: &Temperament,
+    library: &SpeciesLibrary,
+    fish: &Fish,
+    rng: &mut impl Rng,
+) -> Behaviour {
+    let mut appeal = lure.appeal_to(library, fish.species());
+    if twitched {
+        appeal += temperament.twitch_appeal;
+    }
+    if rng.random::<f32>() < appeal {
+        Behaviour::Approach { hook }
+    } else {
+        Behaviour::Ignore { hook }
+    }
+}
+
+/// Moves each free fish through its `Behaviour`. Fish lose interest once a
+/// lure is reeled in, left behind or out of sight, and may decide differently
+/// the next time they see it. Fish sheltering behind obstacles see less far.
+#[allow(clippy::type_complexity)]
+#[allow(clippy::too_many_arguments)]
+pub fn behave(
+    fish_query: Query<(&mut Fish, &Transform, Has<Sheltered>), Without<HookedBy>>,
+    hooks: Query<(Entity, &Transform, &Hook, &Lure, &LureMotion)>,
+    species_assets: Res<Assets<Species>>,
+    library: Res<SpeciesLibrary>,
+    config: Res<Config>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
+    let secs = time.delta_secs();
+    for (mut fish, transform, sheltered) in fish_query {
+        let Some(species) = species_assets.get(fish.species()) else {
+            continue;
+        };
+        let temperament = &species.temperament;
+        let sight = if sheltered {
+            temperament.sight * Sheltered::SIGHT
+        } else {
+            temperament.sight
+        };
+        let position = transform.translation.truncate();
+        let mouth = fish.hitbox(species, position).mouth;
+        // The lure on `entity` if the fish can see it, with how far it is
+        // from the mouth and how it has been moving
+        let sighted = |entity: Entity| {
+            let (_, hook_transform, hook, lure, motion) = hooks.get(entity).ok()?;
+            let lure = lure.config(&config)?;
+            let lure_position = hook_transform.translation.truncate();
+            let in_sight = hook.in_water()
+                && !hook.hooked
+                && fish.sees(position, lure_position, lure.attraction_radius * sight);
+            in_sight.then_some((lure, mouth.distance(lure_position), motion))
+        };
+
+        let behaviour = fish.state.behaviour;
+        fish.state.behaviour = match behaviour {
+            Behaviour::Spook { secs: left } if left > secs => {
+                Behaviour::Spook { secs: left - secs }
+            }
+            Behaviour::Spook { .. } => Behaviour::Cruise,
+            // Left to `predator::hunt`
+            Behaviour::Hunt { .. } | Behaviour::Chase { .. } => behaviour,
+            Behaviour::Cruise => {
+                let in_sight: Vec<_> = hooks
+                    .iter()
+                    .filter_map(|(entity, ..)| sighted(entity).map(|seen| (entity, seen)))
+                    .collect();
+                if in_sight
+                    .iter()
+                    .any(|(_, (_, _, motion))| motion.jerk > temperament.wariness)
+                {
+                    Behaviour::Spook {
+                        secs: species.spook_time,
+                    }
+                } else if let Some((hook, _)) =
+                    in_sight.into_iter().min_by(|a, b| a.1.1.total_cmp(&b.1.1))
+                {
+                    // The nearest lure catches the fish's eye
+                    Behaviour::Notice {
+                        hook,
+                        secs: temperament.notice_time,
+                        twitched: false,
+                    }
+                } else {
+                    Behaviour::Cruise
+                }
+            }
+            _ => {
+                let Some(hook) = behaviour.hook() else {
+                    continue;
+                };
+                match sighted(hook) {
+                    None => Behaviour::Cruise,
+                    Some((_, _, motion)) if motion.jerk > temperament.wariness => {
+                        Behaviour::Spook {
+                            secs: species.spook_time,
+                        }
+                    }
+                    Some((lure, distance, motion)) => match behaviour {
+                        Behaviour::Notice {
+                            secs: left,
+                            twitched,
+                            ..
+                        } => {
+                            let twitched = twitched || motion.twitched;
+                            if left > secs {
+                                Behaviour::Notice {
+                                    hook,
+                                    secs: left - secs,
+                                    twitched,
+                                }
+                            } else {
+                                decide(
+                                    hook,
+                                    lure,
+                                    twitched,
+                                    temperament,
+                                    &library,
+                                    &fish,
+                                    &mut rng.lure,
+                                )
+                            }
+                        }
+                        Behaviour::Approach { .. } if distance < temperament.strike_range => {
+                            Behaviour::Strike { hook }
+                        }
+                        other => other,
+                    },
+                }
+            }
+        };
+    }
+}
@@ -0,0 +1,321 @@
+//! Boss fish. A stage may have one boss, a unique named fish that shows up
+//! partway through the stage. Once hooked it fights in phases, making runs,
+//! dives and jumps, and landing it earns its own reward and may clear the
+//! stage outright.
+
+use crate::{
+    environment::{
+        fish::{self, Fish, HookedBy},
+        species::{Species, SpeciesLibrary},
+    },
+    game_manager::{
+        config::{Config, SpawnEntry, StageConfig},
+        replay::RunInput,
+        rng::GameRng,
+        state::{CountdownTimer, GameState, NextStageEvent},
+    },
+    physics::Velocity,
+    player::{
+        OwnedByPlayer, Player,
+        hook::{Hook, HookLostEvent},
+    },
+    utils::ui::{BossBar, BossMeter, BossName},
+};
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// A stage's boss
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BossConfig {
+    /// Shown in the HUD while the boss is in the lake
+    pub name: String,
+    /// Path to the boss's `*.species.ron` file from `assets/`, giving its
+    /// sprite and stats
+    pub species: String,
+    /// Seconds into the stage before the boss appears
+    pub appears_after: f32,
+    /// Score for landing the boss, on top of what its species is worth
+    pub reward: u32,
+    /// Landing the boss clears the stage whatever the score
+    #[serde(default)]
+    pub clears_stage: bool,
+    /// What the boss does while hooked, in order. Starts over after the last
+    /// phase.
+    pub phases: Vec<BossPhase>,
+}
+
+/// One phase of a boss fight
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BossPhase {
+    pub action: BossMove,
+    /// How long the phase lasts, in seconds
+    pub secs: f32,
+}
+
+/// What a hooked boss does during a phase, on top of fighting like any other
+/// fish. Speeds are in pixels per second.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum BossMove {
+    /// Nothing more than the usual fight
+    Struggle,
+    /// Tears off the way it pulls, dragging the hook along
+    Run(f32),
+    /// Heads for the lakebed, dragging the hook down
+    Dive(f32),
+    /// Leaps for the surface, lifting the hook. Reeling meanwhile strains the
+    /// line hard.
+    Jump(f32),
+}
+
+impl BossMove {
+    /// Shown next to the boss's name in the HUD
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Struggle => "",
+            Self::Run(_) => "RUNS!",
+            Self::Dive(_) => "DIVES!",
+            Self::Jump(_) => "JUMPS!",
+        }
+    }
+}
+
+/// Marks the current stage's boss
+#[derive(Component, Default)]
+pub struct Boss {
+    /// Index into `BossConfig::phases`. `None` until the boss is hooked.
+    phase: Option<usize>,
+    /// Seconds left in the current phase
+    secs: f32,
+}
+
+impl Boss {
+    /// Fraction of the line's strength that reeling through a jump adds to
+    /// its tension each second
+    const JUMP_STRAIN: f32 = 0.5;
+
+    /// What the boss is doing in the fight, if it is hooked
+    fn action(&self, boss: &BossConfig) -> Option<BossMove> {
+        Some(boss.phases.get(self.phase?)?.action)
+    }
+}
+
+/// How the current stage's boss encounter has gone
+#[derive(Resource, Default)]
+pub struct BossEncounter {
+    /// The boss has shown up this stage. It only shows up once, so a boss
+    /// that gets away is gone for the rest of the stage.
+    appeared: bool,
+    /// The boss was landed this stage
+    pub landed: bool,
+}
+
+impl BossEncounter {
+    /// True once landing `stage`'s boss has cleared it
+    pub fn cleared(&self, stage: &StageConfig) -> bool {
+        self.landed && stage.boss.as_ref().is_some_and(|boss| boss.clears_stage)
+    }
+}
+
+/// Every stage starts without its boss
+pub fn on_next_stage(_: On<NextStageEvent>, mut encounter: ResMut<BossEncounter>) {
+    *encounter = BossEncounter::default();
+}
+
+/// Brings the stage's boss into the lake once `BossConfig::appears_after`
+/// seconds of the stage have passed
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_boss(
+    mut commands: Commands,
+    mut encounter: ResMut<BossEncounter>,
+    mut state: ResMut<GameState>,
+    config: Res<Config>,
+    countdown: Single<&CountdownTimer>,
+    asset_server: Res<AssetServer>,
+    library: Res<SpeciesLibrary>,
+    species_assets: Res<Assets<Species>>,
+    mut rng: ResMut<GameRng>,
+) {
+    let stage = state.cur_stage(&config);
+    let Some(boss) = &stage.boss else {
+        return;
+    };
+    if encounter.appeared || countdown.timer.elapsed_secs() < boss.appears_after {
+        return;
+    }
+    // A boss that can't spawn is given up on, rather than retried every tick
+    let Some(handle) = library.get(&boss.species) else {
+        warn!("Boss {} has no species {} loaded", boss.name, boss.species);
+        encounter.appeared = true;
+        return;
+    };
+    let Some(species) = species_assets.get(handle) else {
+        if asset_server.load_state(handle).is_failed() {
+            warn!(
+                "Boss {} can't appear, its species {} failed to load",
+                boss.name, boss.species
+            );
+            encounter.appeared = true;
+        }
+        return;
+    };
+    let Some(depth) =
+        fish::spawn_depth(&SpawnEntry::new(&boss.species), species, stage.water_depth)
+    else {
+        warn!(
+            "Boss {} can't appear, it lives below the lakebed",
+            boss.name
+        );
+        encounter.appeared = true;
+        return;
+    };
+
+    let entity = Fish::spawn_new(
+        handle.clone(),
+        species,
+        &depth,
+        &mut rng.spawn,
+        &mut commands,
+        &asset_server,
+        &config,
+    );
+    commands.entity(entity).insert(Boss::default());
+    state.fish_count += 1;
+    encounter.appeared = true;
+}
+
+/// Reports bosses whose species lives deeper than their stage's water, once
+/// the species loads. They would never appear.
+pub fn report_unreachable_bosses(
+    mut events: MessageReader<AssetEvent<Species>>,
+    config: Res<Config>,
+    library: Res<SpeciesLibrary>,
+    species_assets: Res<Assets<Species>>,
+) {
+    for event in events.read() {
+        let AssetEvent::LoadedWithDependencies { id } = event else {
+            continue;
+        };
+        let Some(species) = species_assets.get(*id) else {
+            continue;
+        };
+        let stages = config
+            .stages
+            .iter()
+            .chain(std::iter::once(&config.sample_stage));
+        for (stage, boss) in stages.filter_map(|stage| Some((stage, stage.boss.as_ref()?))) {
+            let loaded = library
+                .get(&boss.species)
+                .is_some_and(|handle| handle.id() == *id);
+            if loaded
+                && fish::spawn_depth(&SpawnEntry::new(&boss.species), species, stage.water_depth)
+                    .is_none()
+            {
+                error!(
+                    "Boss {} lives deeper than its stage's water_depth of {} and will never appear",
+                    boss.name, stage.water_depth
+                );
+            }
+        }
+    }
+}
+
+/// Puts hooked bosses through their fight phases, pulling their hook on top
+/// of the usual fight. Must run after the hook's velocity is set for the
+/// tick.
+#[allow(clippy::too_many_arguments)]
+pub fn fight(
+    mut commands: Commands,
+    bosses: Query<(&mut Boss, &Fish, &HookedBy)>,
+    mut hooks: Query<(&mut Hook, &mut Velocity, &Transform, &OwnedByPlayer)>,
+    players: Query<&Player>,
+    input: Res<RunInput>,
+    config: Res<Config>,
+    state: Res<GameState>,
+    time: Res<Time>,
+) {
+    let stage = state.cur_stage(&config);
+    let Some(boss_config) = &stage.boss else {
+        return;
+    };
+    let secs = time.delta_secs();
+    let floor = config.water_level - stage.water_depth;
+
+    for (mut boss, fish, hooked_by) in bosses {
+        let Ok((mut hook, mut velocity, transform, owner)) = hooks.get_mut(hooked_by.0) else {
+            continue;
+        };
+        boss.secs -= secs;
+        if boss.secs <= 0. {
+            let next = boss.phase.map_or(0, |phase| phase + 1);
+            let next = next.checked_rem(boss_config.phases.len());
+            boss.phase = next;
+            boss.secs = next.map_or(0., |phase| boss_config.phases[phase].secs);
+        }
+        let reeling = players
+            .get(owner.0)
+            .is_ok_and(|player| input.players[player.index].reel);
+
+        match boss.action(boss_config) {
+            None | Some(BossMove::Struggle) => {}
+            Some(BossMove::Run(speed)) => {
+                // Runs the way it faces until it starts pulling one way
+                let direction = if fish.pulling() != 0. {
+                    fish.pulling()
+                } else {
+                    fish.facing()
+                };
+                velocity.x += direction * speed;
+            }
+            Some(BossMove::Dive(speed)) => velocity.y -= speed,
+            Some(BossMove::Jump(speed)) => {
+                velocity.y += speed;
+                if reeling {
+                    hook.tension += hook.line_strength * Boss::JUMP_STRAIN * secs;
+                }
+            }
+        }
+
+        // Kept in the water, like `handle_input` does
+        let y = transform.translation.y;
+        if (y > config.water_level && velocity.y > 0.) || (y < floor && velocity.y < 0.) {
+            velocity.y = 0.;
+        }
+        // Losing the boss costs its player the encounter, not the run
+        if hook.tension >= hook.line_strength {
+            commands.trigger(HookLostEvent {
+                hook: hooked_by.0,
+                player: owner.0,
+            });
+        }
+    }
+}
+
+/// Shows the boss's name and how much fight it has left while it is in the
+/// lake, along with what it is doing once hooked
+pub fn update_boss_bar(
+    bosses: Query<(&Boss, &Fish)>,
+    bar: Single<&mut Visibility, With<BossBar>>,
+    mut name: Single<&mut Text, With<BossName>>,
+    mut meter: Single<&mut Node, With<BossMeter>>,
+    species_assets: Res<Assets<Species>>,
+    config: Res<Config>,
+    state: Res<GameState>,
+) {
+    let mut visibility = bar.into_inner();
+    let boss_config = state.cur_stage(&config).boss.as_ref();
+    let (Some((boss, fish)), Some(boss_config)) = (bosses.iter().next(), boss_config) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    *visibility = Visibility::Inherited;
+    name.0 = match boss.action(boss_config).map(|action| action.label()) {
+        Some(label) if !label.is_empty() => format!("{} {label}", boss_config.name),
+        _ => boss_config.name.clone(),
+    };
+    let left = species_assets
+        .get(fish.species())
+        .map_or(1., |species| fish.stamina_left(species));
+    meter.width = percent(left * 100.);
+}
@@ -0,0 +1,88 @@
+//! Moving water. Each stage lists bands of current, e.g. a slow surface drift
+//! over still depths or a narrow stream along the lakebed, which carry the
+//! hook and free fish along.
+
+use crate::game_manager::{
+    config::{Config, StageConfig},
+    controls::{Action, ActionInput},
+    state::GameState,
+};
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// A band of moving water in a stage
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CurrentConfig {
+    /// Depth below `water_level` where the band starts
+    pub top: f32,
+    /// Depth below `water_level` where the band ends
+    pub bottom: f32,
+    /// Limits the band to between these two x coordinates, making a stream
+    /// instead of a layer across the whole lake
+    #[serde(default)]
+    pub x: Option<(f32, f32)>,
+    /// How fast the water moves, in pixels per second
+    pub flow: Vec2,
+}
+
+impl CurrentConfig {
+    fn contains(&self, config: &Config, point: Vec2) -> bool {
+        let depth = config.water_level - point.y;
+        let in_x = self
+            .x
+            .is_none_or(|(left, right)| point.x >= left.min(right) && point.x <= left.max(right));
+        in_x && depth >= self.top && depth <= self.bottom
+    }
+}
+
+/// How fast the water moves at `point` in `stage`. Overlapping bands add up.
+pub fn flow_at(config: &Config, stage: &StageConfig, point: Vec2) -> Vec2 {
+    stage
+        .currents
+        .iter()
+        .filter(|current| current.contains(config, point))
+        .map(|current| current.flow)
+        .sum()
+}
+
+/// Shows the current field while present. Toggled with the show currents
+/// action.
+#[derive(Resource)]
+pub struct ShowCurrents;
+
+pub fn toggle_current_gizmos(
+    actions: ActionInput,
+    shown: Option<Res<ShowCurrents>>,
+    mut commands: Commands,
+) {
+    if !actions.just_pressed(Action::ShowCurrents) {
+        return;
+    }
+    if shown.is_some() {
+        commands.remove_resource::<ShowCurrents>();
+    } else {
+        commands.insert_resource(ShowCurrents);
+    }
+}
+
+/// Draws an arrow for the flow at each point of a grid over the lake. Arrows
+/// are as long as the water moves in a second.
+pub fn draw_currents(mut gizmos: Gizmos, config: Res<Config>, state: Res<GameState>) {
+    const SPACING: f32 = 16.;
+    let stage = state.cur_stage(&config);
+    let floor = config.water_level - stage.water_depth;
+    let columns = (config.game_width / SPACING) as i32;
+    let rows = (stage.water_depth / SPACING) as i32;
+    for column in -columns..=columns {
+        for row in 0..=rows {
+            let point = Vec2::new(column as f32 * SPACING, floor + row as f32 * SPACING);
+            let flow = flow_at(&config, stage, point);
+            if flow == Vec2::ZERO {
+                gizmos.circle_2d(point, 0.5, Color::srgba(1., 1., 1., 0.2));
+            } else {
+                gizmos.arrow_2d(point, point + flow, Color::srgb(0.4, 0.8, 1.));
+            }
+        }
+    }
+}
@@ -1,9 +1,27 @@
-use crate::{physics::Velocity, player::hook::HookLostEvent, utils::layers::Layer};
+use crate::{
+    environment::{
+        behaviour::Behaviour,
+        school::Schooling,
+        species::{BodyShape, Species, SpeciesLibrary},
+    },
+    physics::{Drifts, Hitbox, Velocity, grid::Collider},
+    player::hook::HookLostEvent,
+    utils::layers::Layer,
+};
 use bevy::{math::FloatExt, prelude::*, sprite::Anchor};
+use rand::{Rng, seq::IndexedRandom};
+use serde::Deserialize;
 
 use crate::{
-    game_manager::{config::Config, scenes::SceneVolatile, state::GameState},
-    player::hook::{Hook, HookedObjects},
+    game_manager::{
+        config::{Config, DepthBand, SpawnEntry, StageConfig},
+        rng::GameRng,
+        state::{AppState, GameState},
+    },
+    player::{
+        OwnedByPlayer,
+        hook::{Hook, HookedObjects},
+    },
     utils::units::{Inches, Ounces},
 };
 
@@ -24,17 +42,32 @@ impl Direction {
     }
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Default)]
 enum Heading {
+    #[default]
     Left,
     Right,
 }
 
+impl Heading {
+    /// -1 when facing left, 1 when facing right
+    fn sign(&self) -> f32 {
+        match self {
+            Self::Left => -1.,
+            Self::Right => 1.,
+        }
+    }
+}
+
 #[derive(Component)]
-#[require(Transform, Velocity, SceneVolatile)]
+#[require(
+    Transform,
+    Velocity,
+    DespawnOnEnter<AppState> = DespawnOnEnter(AppState::MainMenu)
+)]
 pub struct Fish {
     stats: FishStats,
-    species: &'static Species,
+    species: Handle<Species>,
     pub state: FishState,
 }
 
@@ -45,20 +78,26 @@ pub struct Fish {
 pub struct HookedBy(pub Entity);
 
 impl Fish {
-    fn extra_strength(&self) -> f32 {
-        self.stats.strength - self.species.base_stats.strength
+    /// The species asset this fish was rolled from. Methods needing species
+    /// data take the loaded `&Species` for this handle.
+    pub fn species(&self) -> &Handle<Species> {
+        &self.species
     }
 
-    fn extra_energy(&self) -> f32 {
-        self.stats.energy - self.species.base_stats.energy
+    fn extra_strength(&self, species: &Species) -> f32 {
+        self.stats.strength - species.base_stats.strength
     }
 
-    pub fn get_speed(&self) -> f32 {
-        self.species.base_speed + self.extra_strength() * self.species.strength_to_speed
+    fn extra_energy(&self, species: &Species) -> f32 {
+        self.stats.energy - species.base_stats.energy
     }
 
-    pub fn get_bobbing(&self) -> f32 {
-        self.extra_strength() * self.species.strength_to_bobbing
+    pub fn get_speed(&self, species: &Species) -> f32 {
+        species.base_speed + self.extra_strength(species) * species.strength_to_speed
+    }
+
+    pub fn get_bobbing(&self, species: &Species) -> f32 {
+        self.extra_strength(species) * species.strength_to_bobbing
     }
 
     /// Gets the total velocity of the hook and fish.
@@ -66,39 +105,140 @@ impl Fish {
     /// When in the same direction, total velocity = direction * (hook-speed
     /// + `fish.strength_to_hook` * `fish.stats.strength`).
     ///
-    /// When opposing, the velocity is 0 so it is possible to pull any fish
-    pub fn get_hook_velocity(&self, hook: &Hook, hook_velocity: &Vec2) -> f32 {
-        let hook_speed = hook_velocity.x.abs();
-        let hook_direction = if hook_speed == 0. {
-            0.
-        } else {
-            hook_velocity.x.signum()
-        };
+    /// When opposing, the fish's pull is reduced by the hook's speed. Fighting
+    /// the fish this way strains the line (see [`Fish::get_tension`]). The
+    /// fish's pull weakens as it tires (see [`Fish::get_pull`]).
+    pub fn get_hook_velocity(&self, species: &Species, hook_velocity: &Vec2) -> f32 {
+        let fish_direction = self.state.pulling.clone() as i8 as f32;
+        hook_velocity.x
+            + fish_direction
+                * species.strength_to_hook
+                * self.stats.strength
+                * self.get_pull(species)
+    }
+
+    /// Gets how fast the line's tension builds, per second, for the player's
+    /// `hook_velocity` and whether they are `reeling`.
+    ///
+    /// Tension only builds while the fish is pulling and the player fights it,
+    /// by reeling or by steering against it. Doing both doubles the rate.
+    pub fn get_tension(&self, species: &Species, hook_velocity: &Vec2, reeling: bool) -> f32 {
         let fish_direction = self.state.pulling.clone() as i8 as f32;
         if fish_direction == 0. {
-            // Don't change if fish isn't pulling
-            hook_velocity.x
-        } else if hook_direction == 0. || fish_direction == hook_direction {
-            // Fish has influence with player is in same direction
-            fish_direction * (hook_speed + self.species.strength_to_hook * self.stats.strength)
-        } else {
-            // Player and fish are opposing, do not move
-            0.
+            return 0.;
+        }
+        let opposing = hook_velocity.x * fish_direction < 0.;
+        let strain = u8::from(reeling) + u8::from(opposing);
+        strain as f32
+            * self.get_pull(species)
+            * (species.strength_to_tension * self.stats.strength
+                + species.weight_to_tension * self.stats.weight.0 as f32)
+    }
+
+    /// Stamina of a rested fish, in seconds of fighting the reel
+    pub fn max_stamina(&self, species: &Species) -> f32 {
+        self.stats.energy * species.energy_to_stamina
+            + self.stats.weight.0 as f32 * species.weight_to_stamina
+    }
+
+    /// Fraction of its stamina the fish has left, from 0 when exhausted to 1
+    /// when rested
+    pub fn stamina_left(&self, species: &Species) -> f32 {
+        let max_stamina = self.max_stamina(species);
+        if max_stamina <= 0. {
+            return 1.;
         }
+        (self.state.stamina / max_stamina).clamp(0., 1.)
+    }
+
+    /// How hard the fish can still pull, from 1 when rested down to
+    /// `Species::MIN_PULL` when exhausted
+    pub fn get_pull(&self, species: &Species) -> f32 {
+        Species::MIN_PULL.lerp(1., self.stamina_left(species))
+    }
+
+    /// Drains stamina while the fish fights the reel for `secs`
+    pub fn tire(&mut self, secs: f32) {
+        self.state.stamina = (self.state.stamina - secs).max(0.);
+    }
+
+    /// Regains stamina while the line has been slack for `secs`
+    pub fn recover(&mut self, species: &Species, secs: f32) {
+        self.state.stamina =
+            (self.state.stamina + species.stamina_recovery * secs).min(self.max_stamina(species));
+    }
+
+    pub fn get_frequency(&self, species: &Species) -> f32 {
+        self.extra_energy(species) * species.energy_to_frequency * std::f32::consts::PI * 2.
+    }
+
+    pub fn weight(&self) -> &Ounces {
+        &self.stats.weight
+    }
+
+    /// -1 when the fish faces left, 1 when it faces right
+    pub fn facing(&self) -> f32 {
+        self.stats.heading.sign()
+    }
+
+    /// True while a hooked fish is pulling on the line
+    pub fn struggling(&self) -> bool {
+        !matches!(self.state.pulling, Direction::Neutral)
+    }
+
+    /// Which way a hooked fish is pulling: -1 for left, 1 for right or 0 when
+    /// it isn't
+    pub fn pulling(&self) -> f32 {
+        self.state.pulling.clone() as i8 as f32
     }
 
-    pub fn get_frequency(&self) -> f32 {
-        self.extra_energy() * self.species.energy_to_frequency * std::f32::consts::PI * 2.
+    /// True while the fish is fleeing and won't bite
+    pub fn spooked(&self) -> bool {
+        matches!(self.state.behaviour, Behaviour::Spook { .. })
+    }
+
+    /// True if the fish is striking at `hook`'s lure and will bite it
+    pub fn striking(&self, hook: Entity) -> bool {
+        self.state.behaviour == Behaviour::Strike { hook }
+    }
+
+    /// True if a lure at `lure` is within `radius` and not behind the fish,
+    /// whose snout is at `position`
+    pub fn sees(&self, position: Vec2, lure: Vec2, radius: f32) -> bool {
+        // Lures right at the snout still count as ahead
+        const AHEAD_LENIENCE: f32 = 4.;
+        position.distance(lure) < radius
+            && (lure.x - position.x) * self.stats.heading.sign() > -AHEAD_LENIENCE
+    }
+
+    /// Gets where the fish can be hooked or bumped when its snout (the
+    /// sprite's anchor) is at `position`
+    pub fn hitbox(&self, species: &Species, position: Vec2) -> Hitbox {
+        // Species shapes are in image pixels, measured back from the snout
+        let scale = self.stats.length.0 as f32 / species.img_size.x;
+        let back = -self.stats.heading.sign();
+        let to_world = |point: Vec2| position + Vec2::new(point.x * back, point.y) * scale;
+        let (body_from, body_to, body_radius) = match &species.body {
+            BodyShape::Circle { center, radius } => (*center, *center, *radius),
+            BodyShape::Capsule { from, to, radius } => (*from, *to, *radius),
+        };
+        Hitbox {
+            mouth: to_world(species.mouth),
+            body_from: to_world(body_from),
+            body_to: to_world(body_to),
+            body_radius: body_radius * scale,
+        }
     }
 
     /// Gets the scoring value of a fish
-    pub fn get_score(&self) -> u32 {
-        self.species.base_score + self.stats.weight.0 * self.stats.length.0
+    pub fn get_score(&self, species: &Species) -> u32 {
+        species.base_score + self.stats.weight.0 * self.stats.length.0
     }
 }
 
 /// Statistics to determine how a fish of a species behaves
-#[derive(Clone)]
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct FishStats {
     /// How difficult it is to reel. Primary factor
     /// In scoring. Measured in ounces.
@@ -107,11 +247,13 @@ pub struct FishStats {
     pub length: Inches,
     /// How difficult it is to wrangle the fish horizontally.
     pub strength: f32,
-    /// Determines how frequently the fish will change direction
+    /// Determines how frequently the fish will change direction and, with
+    /// weight, how long it can fight the reel
     pub energy: f32,
     /// How far deep the fish will spawn
     pub depth: Inches,
-    /// Which way the fish is facing
+    /// Which way the fish is facing. Rolled per fish, not read from species.
+    #[serde(skip)]
     heading: Heading,
 }
 
@@ -119,6 +261,10 @@ pub struct FishState {
     pub hooked: bool,
     age: f32,
     pulling: Direction,
+    /// Seconds of fighting the reel left before the fish is exhausted
+    stamina: f32,
+    /// What the fish is doing while free
+    pub behaviour: Behaviour,
     pub timer: Timer,
 }
 
@@ -128,6 +274,8 @@ impl Default for FishState {
             hooked: false,
             age: 0.,
             pulling: Direction::Neutral,
+            stamina: 0.,
+            behaviour: Behaviour::Cruise,
             timer: Timer::from_seconds(0., TimerMode::Once),
         }
     }
@@ -135,15 +283,20 @@ impl Default for FishState {
 
 impl Fish {
     /// Creates a new instance of a fish with randomized stats in between
-    /// base stats and max stats
-    fn new(species: &'static Species) -> Fish {
-        let w_weight = rand::random::<f32>();
-        let w_length = rand::random::<f32>();
-        let w_strength = rand::random::<f32>();
-        let w_energy = rand::random::<f32>();
-        let w_depth = rand::random::<f32>();
-        let roll_heading = rand::random::<f32>();
-        Fish {
+    /// base stats and max stats. Depth is rolled within `depth` instead.
+    fn new(
+        handle: Handle<Species>,
+        species: &Species,
+        depth: &DepthBand,
+        rng: &mut impl Rng,
+    ) -> Fish {
+        let w_weight = rng.random::<f32>();
+        let w_length = rng.random::<f32>();
+        let w_strength = rng.random::<f32>();
+        let w_energy = rng.random::<f32>();
+        let w_depth = rng.random::<f32>();
+        let roll_heading = rng.random::<f32>();
+        let mut fish = Fish {
             stats: FishStats {
                 weight: species
                     .base_stats
@@ -161,126 +314,142 @@ impl Fish {
                     .base_stats
                     .energy
                     .lerp(species.max_stats.energy, w_energy),
-                depth: species
-                    .base_stats
-                    .depth
-                    .lerp(&species.max_stats.depth, w_depth),
+                depth: depth.min.lerp(&depth.max, w_depth),
                 heading: if roll_heading >= 0.5 {
                     Heading::Left
                 } else {
                     Heading::Right
                 },
             },
-            species: &species,
+            species: handle,
             state: FishState::default(),
-        }
+        };
+        fish.state.stamina = fish.max_stamina(species);
+        fish
     }
 
-    /// Spawns a new fish into Bevy
-    fn spawn_new(
-        species: &'static Species,
-        mut commands: Commands,
-        asset_server: Res<AssetServer>,
-        config: &Res<Config>,
-        state: &GameState,
-    ) {
-        let mut fish = Fish::new(&species);
+    /// Spawns a new fish into Bevy at the edge of the lake it swims away from
+    pub(crate) fn spawn_new(
+        handle: Handle<Species>,
+        species: &Species,
+        depth: &DepthBand,
+        rng: &mut impl Rng,
+        commands: &mut Commands,
+        asset_server: &AssetServer,
+        config: &Config,
+    ) -> Entity {
+        let fish = Fish::new(handle, species, depth, rng);
+        let x = config.game_width * -fish.stats.heading.sign();
+        fish.spawn(species, x, commands, asset_server, config)
+    }
 
-        // If under floor, re-roll depth with a floor as a boundary
-        let floor_depth = state.cur_stage(config).water_depth as u32;
-        if fish.stats.depth.0 > floor_depth {
-            let w_depth = rand::random::<f32>();
-            fish.stats.depth = species.base_stats.depth.lerp(&Inches(floor_depth), w_depth)
+    /// Spawns `size` fish swimming together into the lake from the same
+    /// edge, close to the depth of the first one
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn spawn_school(
+        handle: Handle<Species>,
+        species: &Species,
+        school: &Schooling,
+        size: u32,
+        depth: &DepthBand,
+        rng: &mut impl Rng,
+        commands: &mut Commands,
+        asset_server: &AssetServer,
+        config: &Config,
+    ) {
+        let first = Fish::new(handle.clone(), species, depth, rng);
+        let heading = first.stats.heading.clone();
+        let school_depth = first.stats.depth.0 as f32;
+        let mut members = vec![first];
+        for _ in 1..size {
+            let mut fish = Fish::new(handle.clone(), species, depth, rng);
+            fish.stats.heading = heading.clone();
+            let offset = school.cohesion_radius * (rng.random::<f32>() - 0.5);
+            fish.stats.depth = Inches(
+                (school_depth + offset).clamp(depth.min.0 as f32, depth.max.0 as f32) as u32,
+            );
+            members.push(fish);
+        }
+        // Spread out just inside the edge they swim away from
+        let invert = heading.sign();
+        for fish in members {
+            let x = invert * (school.cohesion_radius * rng.random::<f32>() - config.game_width);
+            fish.spawn(species, x, commands, asset_server, config);
         }
+    }
+
+    /// Spawns the fish into Bevy at `x`, at its depth and swimming the way
+    /// it faces
+    fn spawn(
+        self,
+        species: &Species,
+        x: f32,
+        commands: &mut Commands,
+        asset_server: &AssetServer,
+        config: &Config,
+    ) -> Entity {
+        let fish = self;
         let y = config.water_level - fish.stats.depth.0 as f32;
 
         let facing_left = fish.stats.heading == Heading::Left;
-        let invert = if facing_left { -1. } else { 1. };
-        let speed = fish.get_speed();
+        let invert = fish.stats.heading.sign();
+        let speed = fish.get_speed(species);
         // 1in -> 1px
-        let scale = fish.stats.length.0 as f32 / fish.species.img_size.x;
-
-        commands.spawn((
-            fish,
-            Sprite {
-                image: asset_server.load(species.img_path),
-                flip_x: facing_left,
-                ..default()
-            },
-            Transform {
-                translation: Vec3::new(config.game_width * -invert, y, Layer::FISH),
-                scale: Vec3::ONE * scale,
-                ..default()
-            },
-            if facing_left {
-                Anchor::CENTER_LEFT
-            } else {
-                Anchor::CENTER_RIGHT
-            },
-            Velocity(Vec2::new(speed * invert, 0.)),
-        ));
-    }
-}
+        let scale = fish.stats.length.0 as f32 / species.img_size.x;
+        // Reaches from the snout to the tip of the tail
+        let collider = Collider {
+            radius: fish.stats.length.0 as f32,
+        };
 
-/// Representation of a specimen of fish. Should be constructed at start and be
-/// static throughout the game.
-pub struct Species {
-    /// Path to sprite image from `assets/`
-    img_path: &'static str,
-    img_size: Vec2,
-    /// Fish struct containing minimum stats
-    base_stats: FishStats,
-    /// Fish struct containing maximum stats
-    max_stats: FishStats,
-    /// How much each additional unit of strength from minimum should be turned
-    /// into horizontal speed
-    strength_to_speed: f32,
-    /// The base amount of score obtained when catching
-    base_score: u32,
-    base_speed: f32,
-    /// How much each additional unit of strength from minimum should be turned
-    /// into sinusoidal vertical speed (bobbing)
-    strength_to_bobbing: f32,
-    /// How much each additional unit of strength from minimum should be turned
-    /// into velocity applied the hook
-    strength_to_hook: f32,
-    /// How much additional unit of energy from minimum should be turned into
-    /// bobbing frequency
-    energy_to_frequency: f32,
-    /// The maximum amount of time it can take to turn around
-    struggle_time: f32,
+        commands
+            .spawn((
+                fish,
+                Sprite {
+                    image: asset_server.load(&species.img_path),
+                    flip_x: facing_left,
+                    ..default()
+                },
+                Transform {
+                    translation: Vec3::new(x, y, Layer::FISH),
+                    scale: Vec3::ONE * scale,
+                    ..default()
+                },
+                if facing_left {
+                    Anchor::CENTER_LEFT
+                } else {
+                    Anchor::CENTER_RIGHT
+                },
+                Velocity(Vec2::new(speed * invert, 0.)),
+                Drifts,
+                collider,
+            ))
+            .id()
+    }
 }
 
-impl Species {
-    pub const MIN_STRUGGLE: f32 = 0.08;
-    pub const MAX_STRUGGLE: f32 = 5.;
-    pub const BASS: Species = Species {
-        img_path: "bass.png",
-        img_size: Vec2::new(32., 16.),
-        base_stats: FishStats {
-            weight: Ounces(6),
-            length: Inches(10),
-            strength: 5.,
-            energy: 1.,
-            depth: Inches::from_ft_ins(2, 6),
-            heading: Heading::Left,
-        },
-        max_stats: FishStats {
-            weight: Ounces::from_lbs_ozs(10, 5),
-            length: Inches(29),
-            strength: 10.,
-            energy: 1.5,
-            depth: Inches::from_ft_ins(40, 0),
-            heading: Heading::Left,
-        },
-        strength_to_speed: 3.,
-        base_score: 100,
-        base_speed: 3.,
-        strength_to_bobbing: 2.5,
-        strength_to_hook: 10.25,
-        energy_to_frequency: 0.6,
-        struggle_time: 2.,
-    };
+/// Gets the depths a fish from `entry` may spawn at above a floor
+/// `water_depth` deep. Uses the entry's depth band if it has one, otherwise the
+/// species' depth range.
+///
+/// Returns `None` when the floor is shallower than the minimum depth, else
+/// clamps the maximum depth to the floor.
+pub(crate) fn spawn_depth(
+    entry: &SpawnEntry,
+    species: &Species,
+    water_depth: f32,
+) -> Option<DepthBand> {
+    let band = entry.depth.clone().unwrap_or_else(|| DepthBand {
+        min: species.base_stats.depth.clone(),
+        max: species.max_stats.depth.clone(),
+    });
+    let floor_depth = water_depth.max(0.) as u32;
+    if band.min.0 > floor_depth {
+        return None;
+    }
+    Some(DepthBand {
+        max: Inches(band.max.0.clamp(band.min.0, floor_depth)),
+        min: band.min,
+    })
 }
 
 #[derive(Component)]
@@ -293,99 +462,319 @@ pub struct FishEscapedEvent {
     pub entity: Entity,
 }
 
+/// A hook bumped into the body of a fish, rather than its mouth
+#[derive(Event)]
+pub struct FishBumpedEvent {
+    pub fish: Entity,
+}
+
+/// A hooked fish was pulled out of the water by `player`
 #[derive(Event)]
 pub struct FishExtractedEvent {
     pub fish: Entity,
     pub player: Entity,
 }
 
+/// Picks which species to spawn next from `stage`'s spawn table, the depths
+/// it may spawn at and how many more of it may be alive. Only picks from
+/// entries that are loaded and allowed to spawn now.
+pub(crate) fn roll_species<'a>(
+    stage: &StageConfig,
+    library: &'a SpeciesLibrary,
+    species_assets: &'a Assets<Species>,
+    fish_query: &Query<&Fish>,
+    rng: &mut impl Rng,
+) -> Option<(&'a Handle<Species>, &'a Species, DepthBand, u32)> {
+    let candidates: Vec<_> = stage
+        .spawn_table
+        .iter()
+        .filter_map(|entry| {
+            let handle = library.get(&entry.species)?;
+            let species = species_assets.get(handle)?;
+            let depth = spawn_depth(entry, species, stage.water_depth)?;
+            let room = match entry.max_concurrent {
+                Some(max_concurrent) => {
+                    let alive = fish_query.iter().filter(|f| f.species() == handle);
+                    max_concurrent.checked_sub(alive.count() as u32)?
+                }
+                None => u32::MAX,
+            };
+            if room == 0 {
+                return None;
+            }
+            Some((entry, handle, species, depth, room))
+        })
+        .collect();
+    let (_, handle, species, depth, room) = candidates
+        .choose_weighted(rng, |(entry, ..)| entry.weight)
+        .ok()?;
+    Some((handle, species, depth.clone(), *room))
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn handle_spawn(
-    commands: Commands,
+    mut commands: Commands,
     mut spawn_handler: Single<&mut SpawnHandler>,
     config: Res<Config>,
     mut state: ResMut<GameState>,
     time: Res<Time>,
     asset_server: Res<AssetServer>,
+    library: Res<SpeciesLibrary>,
+    species_assets: Res<Assets<Species>>,
+    fish_query: Query<&Fish>,
+    mut rng: ResMut<GameRng>,
 ) {
+    let rng = &mut rng.spawn;
     let fish_count = state.fish_count;
-    let max_fish = state.cur_stage(&config).max_fish;
-    if spawn_handler.timer.is_finished() && fish_count < max_fish {
-        let new_interval: f32 = 5.0 * rand::random::<f32>() + 1.0;
+    let stage = state.cur_stage(&config);
+    if spawn_handler.timer.is_finished() && fish_count < stage.max_fish {
+        let Some((handle, species, depth, room)) =
+            roll_species(stage, &library, &species_assets, &fish_query, rng)
+        else {
+            return;
+        };
+        let room = room.min(stage.max_fish - fish_count);
+
+        let new_interval: f32 = 5.0 * rng.random::<f32>() + 1.0;
         spawn_handler.timer = Timer::from_seconds(new_interval, TimerMode::Once);
-        state.fish_count += 1;
-        Fish::spawn_new(
-            &Species::BASS,
-            commands,
-            asset_server,
-            &config,
-            &state.into_inner(),
-        );
+        if let Some(school) = &species.school {
+            let size = school.roll_size(rng).min(room);
+            state.fish_count += size;
+            Fish::spawn_school(
+                handle.clone(),
+                species,
+                school,
+                size,
+                &depth,
+                rng,
+                &mut commands,
+                &asset_server,
+                &config,
+            );
+        } else {
+            state.fish_count += 1;
+            Fish::spawn_new(
+                handle.clone(),
+                species,
+                &depth,
+                rng,
+                &mut commands,
+                &asset_server,
+                &config,
+            );
+        }
     }
     spawn_handler.timer.tick(time.delta());
 }
 
 /// Moves and despawns fish
 pub fn update_fish(
+    species_assets: Res<Assets<Species>>,
     fish_query: Query<(
         Entity,
         &mut Fish,
-        &GlobalTransform,
+        &Transform,
         &mut Velocity,
         Option<&HookedBy>,
     )>,
+    hooks: Query<&Transform, With<Hook>>,
     mut commands: Commands,
     config: Res<Config>,
+    state: Res<GameState>,
     time: Res<Time>,
 ) {
     const ESCAPE_LENIENCE: f32 = 1.;
+    let stage = state.cur_stage(&config);
     for (entity, mut fish, transform, mut velocity, hooked_by) in fish_query {
+        // Hooked fish are positioned relative to their hook. `GlobalTransform`
+        // isn't used as it is only updated once per frame, not per tick.
+        let mut x = transform.translation.x;
+        if let Some(hook_transform) = hooked_by.and_then(|h| hooks.get(h.0).ok()) {
+            x += hook_transform.translation.x;
+        }
         // Despawn escaped fish
-        if transform.translation().x.abs() > config.game_width + ESCAPE_LENIENCE {
+        if x.abs() > config.game_width + ESCAPE_LENIENCE {
             commands.trigger(FishEscapedEvent { entity });
             continue;
         }
 
-        if let Some(_) = hooked_by {
+        if hooked_by.is_some() {
             continue;
         }
+        let Some(species) = species_assets.get(fish.species()) else {
+            continue;
+        };
 
         fish.state.age += time.delta_secs();
+        let mut speed = fish.get_speed(species);
+        if fish.spooked() {
+            speed *= species.flee_speed;
+        }
+        let climb = species
+            .water
+            .climb(&config, stage, transform.translation.truncate());
         *velocity = Velocity(Vec2::new(
-            velocity.x,
-            fish.get_bobbing() * ops::cos(fish.state.age * fish.get_frequency()),
+            fish.stats.heading.sign() * speed,
+            fish.get_bobbing(species) * ops::cos(fish.state.age * fish.get_frequency(species))
+                + climb * Species::CLIMB_SPEED,
         ));
+
+        let behaviour = fish.state.behaviour;
+        if let Behaviour::Chase { at, .. } = behaviour {
+            let mouth = fish.hitbox(species, transform.translation.truncate()).mouth;
+            let chase_speed = species.predator.as_ref().map_or(1., |p| p.chase_speed);
+            velocity.0 = (at - mouth).normalize_or_zero() * speed * chase_speed;
+            continue;
+        }
+
+        // Fish with an eye on a lure swim to its depth, or straight at it
+        // when striking. Hunting predators go straight for the hook's catch.
+        let Some(lure) = behaviour
+            .hook()
+            .and_then(|hook| hooks.get(hook).ok())
+            .map(|hook_transform| hook_transform.translation.truncate())
+        else {
+            continue;
+        };
+        let position = transform.translation.truncate();
+        match behaviour {
+            Behaviour::Notice { .. } | Behaviour::Approach { .. } => {
+                let climb_speed = speed.max(Species::CLIMB_SPEED);
+                velocity.y =
+                    ((lure.y - position.y) * Behaviour::HOMING).clamp(-climb_speed, climb_speed);
+                if let Behaviour::Notice { .. } = behaviour {
+                    velocity.x *= Behaviour::NOTICE_SPEED;
+                }
+            }
+            Behaviour::Strike { .. } => {
+                let mouth = fish.hitbox(species, position).mouth;
+                velocity.0 = (lure - mouth).normalize_or_zero()
+                    * speed.max(Species::CLIMB_SPEED)
+                    * species.temperament.strike_speed;
+            }
+            Behaviour::Hunt { .. } => {
+                let mouth = fish.hitbox(species, position).mouth;
+                let chase_speed = species.predator.as_ref().map_or(1., |p| p.chase_speed);
+                velocity.0 = (lure - mouth).normalize_or_zero() * speed * chase_speed;
+            }
+            _ => {}
+        }
     }
 }
 
-/// Allows fish to periodically change direction using random timers
-pub fn struggle(fish_query: Single<&mut Fish, With<HookedBy>>, time: Res<Time>) {
-    let mut fish = fish_query.into_inner();
-    if fish.state.timer.is_finished() {
-        let new_interval: f32 = (fish.species.struggle_time * rand::random::<f32>())
+/// Allows hooked fish to periodically change direction using random timers
+pub fn struggle(
+    fish_query: Query<&mut Fish, With<HookedBy>>,
+    species_assets: Res<Assets<Species>>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
+    for mut fish in fish_query {
+        let Some(species) = species_assets.get(fish.species()) else {
+            continue;
+        };
+        if fish.state.timer.is_finished() {
+            // Tired fish take longer to change direction
+            let new_interval: f32 = (species.struggle_time * rng.struggle.random::<f32>()
+                / fish.get_pull(species))
             .clamp(Species::MIN_STRUGGLE, Species::MAX_STRUGGLE);
-        fish.state.timer = Timer::from_seconds(new_interval, TimerMode::Once);
-        // Change direction
-        let diff = fish.state.pulling.different();
-        fish.state.pulling = if rand::random::<f32>() >= 0.5 {
-            diff.0
-        } else {
-            diff.1
+            fish.state.timer = Timer::from_seconds(new_interval, TimerMode::Once);
+            // Change direction
+            let diff = fish.state.pulling.different();
+            fish.state.pulling = if rng.struggle.random::<f32>() >= 0.5 {
+                diff.0
+            } else {
+                diff.1
+            };
+        }
+        fish.state.timer.tick(time.delta());
+    }
+}
+
+/// Sends a bumped fish fleeing
+pub fn on_fish_bumped(
+    event: On<FishBumpedEvent>,
+    mut fish_query: Query<&mut Fish>,
+    species_assets: Res<Assets<Species>>,
+) {
+    let Ok(mut fish) = fish_query.get_mut(event.fish) else {
+        return;
+    };
+    if let Some(species) = species_assets.get(fish.species()) {
+        fish.state.behaviour = Behaviour::Spook {
+            secs: species.spook_time,
         };
     }
-    fish.state.timer.tick(time.delta());
 }
 
 pub fn on_fish_escape(
     event: On<FishEscapedEvent>,
     mut commands: Commands,
     fish_query: Query<Option<&HookedBy>, With<Fish>>,
+    hooks: Query<&OwnedByPlayer, With<Hook>>,
     mut state: ResMut<GameState>,
 ) {
     let entity = event.event().entity;
     commands.entity(entity).despawn();
     state.fish_count -= 1;
 
-    if let Ok(Some(_)) = fish_query.get(entity) {
-        commands.trigger(HookLostEvent);
+    if let Ok(Some(hooked_by)) = fish_query.get(entity)
+        && let Ok(owner) = hooks.get(hooked_by.0)
+    {
+        commands.trigger(HookLostEvent {
+            hook: hooked_by.0,
+            player: owner.0,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bass living from 30 to 480 inches deep
+    fn bass() -> Species {
+        ron::from_str(include_str!(
+            "../../../biggun_game/assets/species/bass.species.ron"
+        ))
+        .unwrap()
+    }
+
+    fn banded(min: u32, max: u32) -> SpawnEntry {
+        SpawnEntry {
+            depth: Some(DepthBand {
+                min: Inches(min),
+                max: Inches(max),
+            }),
+            ..SpawnEntry::new("bass")
+        }
+    }
+
+    fn depths(band: Option<DepthBand>) -> Option<(u32, u32)> {
+        band.map(|band| (band.min.0, band.max.0))
+    }
+
+    #[test]
+    fn skips_species_living_below_the_floor() {
+        let entry = SpawnEntry::new("bass");
+        assert_eq!(depths(spawn_depth(&entry, &bass(), 20.)), None);
+        assert_eq!(depths(spawn_depth(&entry, &bass(), 30.)), Some((30, 30)));
+    }
+
+    #[test]
+    fn clamps_the_band_to_the_floor() {
+        let entry = SpawnEntry::new("bass");
+        assert_eq!(depths(spawn_depth(&entry, &bass(), 150.)), Some((30, 150)));
+        assert_eq!(depths(spawn_depth(&entry, &bass(), 600.)), Some((30, 480)));
+        let entry = banded(50, 300);
+        assert_eq!(depths(spawn_depth(&entry, &bass(), 150.)), Some((50, 150)));
+    }
+
+    #[test]
+    fn skips_a_band_entirely_below_the_floor() {
+        // The stage's band replaces the species range, even when it could spawn
+        let entry = banded(200, 300);
+        assert_eq!(depths(spawn_depth(&entry, &bass(), 150.)), None);
+        assert_eq!(depths(spawn_depth(&entry, &bass(), -10.)), None);
     }
 }
@@ -1,14 +1,57 @@
+use crate::game_manager::{TickSet, state::AppState};
 use bevy::prelude::*;
 
+pub(crate) mod behaviour;
+pub(crate) mod boss;
+pub(crate) mod current;
 pub(crate) mod fish;
+pub(crate) mod obstacle;
+pub(crate) mod predator;
+pub(crate) mod school;
+pub(crate) mod species;
 
 /// Handles the non-player elements of the environment. Notably fish.
 pub struct BiggunEnvironmentPlugin;
 
 impl Plugin for BiggunEnvironmentPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (fish::update_fish, fish::struggle))
-            .add_systems(FixedUpdate, fish::handle_spawn)
-            .add_observer(fish::on_fish_escape);
+        app.init_asset::<species::Species>()
+            .init_resource::<boss::BossEncounter>()
+            .init_asset_loader::<species::SpeciesLoader>()
+            .add_systems(Startup, species::load_species)
+            .add_systems(
+                FixedUpdate,
+                (
+                    fish::handle_spawn.run_if(lake_is_live),
+                    boss::spawn_boss.run_if(in_state(AppState::Playing)),
+                    behaviour::behave.run_if(lake_is_live),
+                    predator::hunt.run_if(in_state(AppState::Playing)),
+                    fish::update_fish.run_if(lake_is_live),
+                    school::flock.run_if(lake_is_live),
+                    fish::struggle.run_if(in_state(AppState::Playing)),
+                    boss::fight.run_if(in_state(AppState::Playing)),
+                )
+                    .chain()
+                    .in_set(TickSet::Fish),
+            )
+            .add_systems(
+                Update,
+                (
+                    species::report_failed_species,
+                    boss::report_unreachable_bosses,
+                    current::toggle_current_gizmos.run_if(in_state(AppState::Playing)),
+                    current::draw_currents.run_if(resource_exists::<current::ShowCurrents>),
+                    boss::update_boss_bar.run_if(in_state(AppState::Playing)),
+                ),
+            )
+            .add_observer(fish::on_fish_escape)
+            .add_observer(fish::on_fish_bumped)
+            .add_observer(boss::on_next_stage);
     }
 }
+
+/// True when fish should move and spawn. The lake is frozen while paused and
+/// on the game over screen.
+pub fn lake_is_live(state: Res<State<AppState>>) -> bool {
+    matches!(state.get(), AppState::MainMenu | AppState::Playing)
+}
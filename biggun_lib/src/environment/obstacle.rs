@@ -0,0 +1,141 @@
+//! Weeds, rocks and sunken logs resting on the lakebed. Fish hide behind
+//! them, hooks are slowed or blocked by them and a fish dragged through them
+//! can snag the line.
+
+use crate::{
+    game_manager::config::{Config, StageConfig},
+    physics::grid::Collider,
+    utils::layers::Layer,
+};
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// What an obstacle is, which decides how it gets in the way
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum ObstacleKind {
+    /// Slows the hook down but lets it through
+    Weeds,
+    /// Blocks the hook
+    Rock,
+    /// Blocks the hook and snags the line badly
+    Log,
+}
+
+impl ObstacleKind {
+    /// Fraction of its speed the hook keeps while inside
+    pub fn drag(&self) -> f32 {
+        match self {
+            Self::Weeds => 0.4,
+            Self::Rock | Self::Log => 1.,
+        }
+    }
+
+    /// True if the hook can't pass through
+    pub fn solid(&self) -> bool {
+        match self {
+            Self::Weeds => false,
+            Self::Rock | Self::Log => true,
+        }
+    }
+
+    /// Tension added to the line per second while a hooked fish is dragged
+    /// through. Has to beat `Hook::slack_recovery` to ever break the line.
+    pub fn snag(&self) -> f32 {
+        match self {
+            Self::Weeds => 45.,
+            Self::Rock => 35.,
+            Self::Log => 90.,
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Self::Weeds => Color::srgba(0.18, 0.45, 0.2, 0.85),
+            Self::Rock => Color::srgb(0.35, 0.35, 0.38),
+            Self::Log => Color::srgb(0.36, 0.24, 0.13),
+        }
+    }
+}
+
+/// An obstacle in a stage, standing on the lakebed
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ObstacleConfig {
+    pub kind: ObstacleKind,
+    /// Horizontal position of its center
+    pub x: f32,
+    pub width: f32,
+    /// How far up from the lakebed it reaches
+    pub height: f32,
+}
+
+/// An obstacle in the lake. Covers `size` around its `Transform`.
+#[derive(Component)]
+#[require(Transform)]
+pub struct Obstacle {
+    pub kind: ObstacleKind,
+    pub size: Vec2,
+}
+
+impl Obstacle {
+    /// Area covered when centered at `center`
+    pub fn rect(&self, center: Vec2) -> Rect {
+        Rect::from_center_size(center, self.size)
+    }
+
+    /// How far `point` must move to get out of the top or sides, if it is
+    /// inside when centered at `center`. Nothing is pushed out the bottom,
+    /// into the lakebed.
+    pub fn overlap(&self, center: Vec2, point: Vec2) -> Option<Vec2> {
+        let rect = self.rect(center);
+        if !rect.contains(point) {
+            return None;
+        }
+        [
+            Vec2::new(rect.min.x - point.x, 0.),
+            Vec2::new(rect.max.x - point.x, 0.),
+            Vec2::new(0., rect.max.y - point.y),
+        ]
+        .into_iter()
+        .min_by(|a, b| a.length().total_cmp(&b.length()))
+    }
+}
+
+/// Marks a fish hidden by an obstacle it is swimming behind. Hidden fish are
+/// drawn behind the obstacle and, tucked away in it, notice lures from less
+/// far away.
+#[derive(Component)]
+pub struct Sheltered;
+
+impl Sheltered {
+    /// Fraction of its usual sight a sheltered fish keeps
+    pub const SIGHT: f32 = 0.5;
+}
+
+/// Replaces any obstacles in the lake with `stage`'s
+pub fn spawn_obstacles(
+    commands: &mut Commands,
+    obstacles: &Query<Entity, With<Obstacle>>,
+    config: &Config,
+    stage: &StageConfig,
+) {
+    for entity in obstacles {
+        commands.entity(entity).despawn();
+    }
+    let floor = config.water_level - stage.water_depth;
+    for obstacle in &stage.obstacles {
+        // Never taller than the water is deep
+        let size = Vec2::new(obstacle.width, obstacle.height.min(stage.water_depth));
+        commands.spawn((
+            Obstacle {
+                kind: obstacle.kind,
+                size,
+            },
+            Sprite::from_color(obstacle.kind.color(), size),
+            Transform::from_xyz(obstacle.x, floor + size.y / 2., Layer::OBSTACLES),
+            Collider {
+                radius: size.length() / 2.,
+            },
+        ));
+    }
+}
@@ -0,0 +1,206 @@
+//! Predators, big fish that prey on smaller catches. A hooked fish fighting
+//! the line draws in any predator close enough, and the first to reach it
+//! either tears it off the hook, cutting the line and costing its player the
+//! catch, or swallows it whole and ends up hooked itself. Otherwise predators
+//! chase schooling fish swimming by, which flee, and eat any they catch.
+
+use crate::{
+    environment::{
+        behaviour::Behaviour,
+        fish::{Fish, HookedBy},
+        species::Species,
+    },
+    game_manager::{rng::GameRng, state::GameState},
+    physics::grid::SpatialGrid,
+    player::{
+        OwnedByPlayer,
+        hook::{Hook, HookEvent, HookLostEvent},
+    },
+};
+use bevy::{platform::collections::HashMap, prelude::*};
+use rand::Rng;
+use serde::Deserialize;
+
+/// How a species preys on hooked fish
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Predation {
+    /// How far away a struggling hooked fish or a free schooling fish is
+    /// sensed
+    pub sense_radius: f32,
+    /// Heaviest prey, as a fraction of the predator's own weight
+    pub max_prey: f32,
+    /// How many times faster than usual a hunting predator swims
+    pub chase_speed: f32,
+    /// How close the mouth must get to the prey to attack it
+    pub strike_range: f32,
+    /// Chance from 0 to 1 that an attack swallows the prey, hooking the
+    /// predator in its place. Otherwise the prey is stolen and the line cut.
+    pub swallow_chance: f32,
+}
+
+impl Default for Predation {
+    fn default() -> Self {
+        Self {
+            sense_radius: 80.,
+            max_prey: 0.5,
+            chase_speed: 2.,
+            strike_range: 10.,
+            swallow_chance: 0.5,
+        }
+    }
+}
+
+/// Sends predators after struggling hooked fish and lets them attack once in
+/// reach. Hunts are called off when the catch is landed or lost. Predators
+/// with no catch to go for chase free schooling fish instead, found through
+/// the `SpatialGrid`, until they eat them or lose sight of them.
+#[allow(clippy::too_many_arguments)]
+pub fn hunt(
+    mut commands: Commands,
+    mut predators: Query<(Entity, &mut Fish, &Transform), Without<HookedBy>>,
+    prey: Query<(Entity, &Fish, &HookedBy)>,
+    hooks: Query<(&Transform, &OwnedByPlayer), With<Hook>>,
+    species_assets: Res<Assets<Species>>,
+    grid: Res<SpatialGrid>,
+    mut state: ResMut<GameState>,
+    mut rng: ResMut<GameRng>,
+) {
+    // Free schooling fish, with where they are and their weight
+    let schooling: HashMap<Entity, (Vec2, f32)> = predators
+        .iter()
+        .filter(|(_, fish, _)| {
+            species_assets
+                .get(fish.species())
+                .is_some_and(|species| species.school.is_some())
+        })
+        .map(|(entity, fish, transform)| {
+            (
+                entity,
+                (transform.translation.truncate(), fish.weight().0 as f32),
+            )
+        })
+        .collect();
+    // Prey attacked this tick, which no other predator can get to
+    let mut taken = Vec::new();
+    // Schooling fish that noticed a predator coming for them
+    let mut fleeing = Vec::new();
+    for (entity, mut fish, transform) in &mut predators {
+        let Some(species) = species_assets.get(fish.species()) else {
+            continue;
+        };
+        let Some(predation) = &species.predator else {
+            continue;
+        };
+        let position = transform.translation.truncate();
+        let max_prey = fish.weight().0 as f32 * predation.max_prey;
+        // Hooked fish small enough to go for, with their hook and where they
+        // are. Hooked fish sit right on their hook.
+        let mut catches = prey
+            .iter()
+            .filter_map(|(prey_entity, prey_fish, hooked_by)| {
+                let at = hooks.get(hooked_by.0).ok()?.0.translation.truncate();
+                (prey_fish.weight().0 as f32 <= max_prey).then_some((
+                    prey_entity,
+                    prey_fish,
+                    hooked_by.0,
+                    at,
+                ))
+            });
+
+        match fish.state.behaviour {
+            Behaviour::Cruise => {
+                // Only a fish fighting the line gives itself away
+                let sensed = catches
+                    .filter(|(_, prey_fish, _, at)| {
+                        prey_fish.struggling() && position.distance(*at) < predation.sense_radius
+                    })
+                    .min_by(|a, b| position.distance(a.3).total_cmp(&position.distance(b.3)));
+                if let Some((_, _, hook, _)) = sensed {
+                    fish.state.behaviour = Behaviour::Hunt { hook };
+                    continue;
+                }
+                let spotted = grid
+                    .query(position, predation.sense_radius)
+                    .into_iter()
+                    .filter(|&other| other != entity)
+                    .filter_map(|other| Some((other, *schooling.get(&other)?)))
+                    .filter(|&(_, (at, weight))| {
+                        weight <= max_prey && position.distance(at) < predation.sense_radius
+                    })
+                    .min_by(|a, b| {
+                        position
+                            .distance(a.1.0)
+                            .total_cmp(&position.distance(b.1.0))
+                    });
+                if let Some((prey, (at, _))) = spotted {
+                    fish.state.behaviour = Behaviour::Chase { prey, at };
+                    fleeing.push(prey);
+                }
+            }
+            Behaviour::Chase { prey, .. } => {
+                // Eaten by another predator, hooked, or out of sight
+                let Some(&(at, _)) = schooling.get(&prey).filter(|(at, _)| {
+                    !taken.contains(&prey) && position.distance(*at) < predation.sense_radius
+                }) else {
+                    fish.state.behaviour = Behaviour::Cruise;
+                    continue;
+                };
+                let mouth = fish.hitbox(species, position).mouth;
+                if mouth.distance(at) > predation.strike_range {
+                    fish.state.behaviour = Behaviour::Chase { prey, at };
+                    continue;
+                }
+                taken.push(prey);
+                commands.entity(prey).despawn();
+                state.fish_count -= 1;
+                fish.state.behaviour = Behaviour::Cruise;
+            }
+            Behaviour::Hunt { hook } => {
+                let Some((prey_entity, _, _, at)) = catches.find(|(_, _, on, _)| *on == hook)
+                else {
+                    fish.state.behaviour = Behaviour::Cruise;
+                    continue;
+                };
+                let mouth = fish.hitbox(species, position).mouth;
+                if mouth.distance(at) > predation.strike_range || taken.contains(&prey_entity) {
+                    continue;
+                }
+                taken.push(prey_entity);
+                if rng.predation.random::<f32>() < predation.swallow_chance {
+                    commands.entity(prey_entity).despawn();
+                    state.fish_count -= 1;
+                    fish.state.behaviour = Behaviour::Cruise;
+                    commands.trigger(HookEvent {
+                        hook_entity: hook,
+                        fish_entity: entity,
+                    });
+                } else if let Ok((_, owner)) = hooks.get(hook) {
+                    // Makes off with the catch, which its player loses like
+                    // any other. The hook goes back to the boat.
+                    fish.state.behaviour = Behaviour::Spook {
+                        secs: species.spook_time,
+                    };
+                    commands.trigger(HookLostEvent {
+                        hook,
+                        player: owner.0,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for prey in fleeing {
+        let Ok((_, mut fish, _)) = predators.get_mut(prey) else {
+            continue;
+        };
+        if let Some(species) = species_assets.get(fish.species())
+            && !taken.contains(&prey)
+        {
+            fish.state.behaviour = Behaviour::Spook {
+                secs: species.spook_time,
+            };
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Schools of small fish. Species with a `school` spawn in groups, and each
+//! free fish in one flocks with the schoolmates around it: keeping its
+//! distance, matching their movement and staying close to them. Predators
+//! chase free schooling fish, see `predator::hunt`.
+
+use crate::{
+    environment::{
+        behaviour::Behaviour,
+        fish::{Fish, HookedBy},
+        species::Species,
+    },
+    physics::{Velocity, grid::SpatialGrid},
+};
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+/// How a species swims in schools. Schoolmates are fish of the same species
+/// heading the same way.
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Schooling {
+    /// Fewest and most fish spawning together
+    pub size: (u32, u32),
+    /// Fish steer away from schoolmates closer than this
+    pub separation_radius: f32,
+    /// Fish match the movement of schoolmates within this
+    pub alignment_radius: f32,
+    /// Fish steer towards the middle of schoolmates within this
+    pub cohesion_radius: f32,
+    /// How fast, in pixels per second, a fish steers away from a schoolmate
+    /// right next to it. Weakens with distance.
+    pub separation: f32,
+    /// Fraction of the difference to its schoolmates' average velocity a fish
+    /// makes up
+    pub alignment: f32,
+    /// How fast fish steer towards the middle of their schoolmates, per pixel
+    /// away from it
+    pub cohesion: f32,
+    /// Most flocking may change a fish's velocity, in pixels per second
+    pub max_steer: f32,
+}
+
+impl Default for Schooling {
+    fn default() -> Self {
+        Self {
+            size: (3, 6),
+            separation_radius: 6.,
+            alignment_radius: 20.,
+            cohesion_radius: 30.,
+            separation: 10.,
+            alignment: 0.5,
+            cohesion: 0.3,
+            max_steer: 6.,
+        }
+    }
+}
+
+impl Schooling {
+    /// Furthest a fish looks for schoolmates
+    fn reach(&self) -> f32 {
+        self.separation_radius
+            .max(self.alignment_radius)
+            .max(self.cohesion_radius)
+    }
+
+    /// How many fish the next school spawns with
+    pub fn roll_size(&self, rng: &mut impl Rng) -> u32 {
+        let (min, max) = self.size;
+        rng.random_range(min..=max.max(min)).max(1)
+    }
+
+    /// How much a fish at `position` moving at `velocity` steers to flock
+    /// with `schoolmates`, given as their positions and velocities
+    fn steer(
+        &self,
+        position: Vec2,
+        velocity: Vec2,
+        schoolmates: impl Iterator<Item = (Vec2, Vec2)>,
+    ) -> Vec2 {
+        let mut separation = Vec2::ZERO;
+        let (mut velocities, mut aligned) = (Vec2::ZERO, 0);
+        let (mut positions, mut cohering) = (Vec2::ZERO, 0);
+        for (other, other_velocity) in schoolmates {
+            let distance = position.distance(other);
+            if distance < self.separation_radius {
+                separation += (position - other).normalize_or_zero()
+                    * (1. - distance / self.separation_radius);
+            }
+            if distance < self.alignment_radius {
+                velocities += other_velocity;
+                aligned += 1;
+            }
+            if distance < self.cohesion_radius {
+                positions += other;
+                cohering += 1;
+            }
+        }
+
+        let mut steer = separation * self.separation;
+        if aligned > 0 {
+            steer += (velocities / aligned as f32 - velocity) * self.alignment;
+        }
+        if cohering > 0 {
+            steer += (positions / cohering as f32 - position) * self.cohesion;
+        }
+        steer.clamp_length_max(self.max_steer)
+    }
+}
+
+/// Steers free fish in schools on top of the velocity `update_fish` gave
+/// them. Fish with an eye on a lure leave their school to go for it.
+/// Schoolmates are found through the `SpatialGrid`, so only nearby fish are
+/// looked at.
+pub fn flock(
+    mut fish_query: Query<(Entity, &Fish, &Transform, &mut Velocity), Without<HookedBy>>,
+    species_assets: Res<Assets<Species>>,
+    grid: Res<SpatialGrid>,
+) {
+    // Worked out before any fish steers, so every fish flocks with where its
+    // schoolmates were going
+    let steers: Vec<_> = fish_query
+        .iter()
+        .filter(|(_, fish, ..)| {
+            matches!(
+                fish.state.behaviour,
+                Behaviour::Cruise | Behaviour::Spook { .. }
+            )
+        })
+        .filter_map(|(entity, fish, transform, velocity)| {
+            let school = species_assets.get(fish.species())?.school.as_ref()?;
+            let position = transform.translation.truncate();
+            let schoolmates = grid
+                .query(position, school.reach())
+                .into_iter()
+                .filter(|&other| other != entity)
+                .filter_map(|other| fish_query.get(other).ok())
+                .filter(|(_, other, ..)| {
+                    other.species() == fish.species() && other.facing() == fish.facing()
+                })
+                .map(|(_, _, transform, velocity)| (transform.translation.truncate(), velocity.0));
+            Some((entity, school.steer(position, velocity.0, schoolmates)))
+        })
+        .collect();
+
+    for (entity, steer) in steers {
+        let Ok((_, fish, _, mut velocity)) = fish_query.get_mut(entity) else {
+            continue;
+        };
+        let facing = fish.facing();
+        velocity.0 += steer;
+        // Schools keep crossing the lake rather than turning back
+        velocity.x = facing * (velocity.x * facing).max(0.);
+    }
+}
@@ -0,0 +1,230 @@
+//! Fish species, loaded as assets from `*.species.ron` files so new species
+//! can be added without touching Rust code.
+
+use crate::{
+    environment::{
+        behaviour::Temperament, current, fish::FishStats, predator::Predation, school::Schooling,
+    },
+    game_manager::config::{Config, StageConfig},
+};
+use bevy::{
+    asset::{AssetLoadFailedEvent, AssetLoader, LoadContext, io::Reader},
+    platform::collections::HashMap,
+    prelude::*,
+};
+use serde::Deserialize;
+
+/// Representation of a specimen of fish. Loaded from a `*.species.ron` file
+/// and shared by every fish of that species.
+#[derive(Asset, TypePath, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Species {
+    /// Path to sprite image from `assets/`
+    pub img_path: String,
+    pub img_size: Vec2,
+    /// Where the mouth is on the sprite, in image pixels back from the snout
+    /// and up from the centre line. Hooks bite here.
+    pub mouth: Vec2,
+    /// Solid part of the fish, in the same units as `mouth`. Hooks touching it
+    /// bump the fish instead of biting.
+    pub body: BodyShape,
+    /// Fish struct containing minimum stats
+    pub base_stats: FishStats,
+    /// Fish struct containing maximum stats
+    pub max_stats: FishStats,
+    /// How much each additional unit of strength from minimum should be turned
+    /// into horizontal speed
+    pub strength_to_speed: f32,
+    /// The base amount of score obtained when catching
+    pub base_score: u32,
+    pub base_speed: f32,
+    /// How much each additional unit of strength from minimum should be turned
+    /// into sinusoidal vertical speed (bobbing)
+    pub strength_to_bobbing: f32,
+    /// How much each additional unit of strength from minimum should be turned
+    /// into velocity applied the hook
+    pub strength_to_hook: f32,
+    /// How much line tension each unit of strength builds per second while
+    /// the player fights the fish
+    pub strength_to_tension: f32,
+    /// How much line tension each ounce of weight builds per second while the
+    /// player fights the fish
+    pub weight_to_tension: f32,
+    /// How much additional unit of energy from minimum should be turned into
+    /// bobbing frequency
+    pub energy_to_frequency: f32,
+    /// The maximum amount of time it can take to turn around
+    pub struggle_time: f32,
+    /// How many seconds of fighting the reel each unit of energy is worth
+    pub energy_to_stamina: f32,
+    /// How many seconds of fighting the reel each ounce of weight is worth
+    pub weight_to_stamina: f32,
+    /// How many seconds of stamina are regained per second of slack line
+    pub stamina_recovery: f32,
+    /// How long a fish flees after being bumped, in seconds
+    pub spook_time: f32,
+    /// How many times faster than usual a fleeing fish swims
+    pub flee_speed: f32,
+    /// Water the species swims up or down towards
+    #[serde(default)]
+    pub water: WaterPreference,
+    /// How the species reacts to lures
+    #[serde(default)]
+    pub temperament: Temperament,
+    /// Makes the species spawn and swim in schools
+    #[serde(default)]
+    pub school: Option<Schooling>,
+    /// Makes the species go after smaller fish on the hook
+    #[serde(default)]
+    pub predator: Option<Predation>,
+}
+
+/// Water a species looks for when a stage has currents
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+pub enum WaterPreference {
+    /// Stays at its depth, drifting with whatever current is there
+    #[default]
+    Any,
+    /// Leaves currents for still water
+    Calm,
+    /// Swims into the strongest current nearby
+    Fast,
+}
+
+impl WaterPreference {
+    /// How far above and below itself a fish looks for better water
+    const PROBE: f32 = 8.;
+
+    /// Which way a fish at `point` should swim to find better water: 1 for
+    /// up, -1 for down or 0 to stay. Never leads out of the water.
+    pub fn climb(&self, config: &Config, stage: &StageConfig, point: Vec2) -> f32 {
+        let sign = match self {
+            Self::Any => return 0.,
+            Self::Calm => -1.,
+            Self::Fast => 1.,
+        };
+        let score = |y: f32| sign * current::flow_at(config, stage, Vec2::new(point.x, y)).length();
+        let floor = config.water_level - stage.water_depth;
+        let here = score(point.y);
+        [1., -1.]
+            .into_iter()
+            .filter(|direction| {
+                let y = point.y + direction * Self::PROBE;
+                y > floor && y < config.water_level
+            })
+            .map(|direction| (direction, score(point.y + direction * Self::PROBE)))
+            .filter(|&(_, score)| score > here)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(0., |(direction, _)| direction)
+    }
+}
+
+/// Shape of a fish's body, in image pixels back from the snout and up from the
+/// centre line. Scaled with the fish's length.
+#[derive(Deserialize, Clone)]
+pub enum BodyShape {
+    Circle {
+        center: Vec2,
+        radius: f32,
+    },
+    /// Every point within `radius` of the segment from `from` to `to`
+    Capsule {
+        from: Vec2,
+        to: Vec2,
+        radius: f32,
+    },
+}
+
+impl Species {
+    pub const MIN_STRUGGLE: f32 = 0.08;
+    pub const MAX_STRUGGLE: f32 = 5.;
+    /// Fraction of its full pull an exhausted fish can still muster
+    pub const MIN_PULL: f32 = 0.25;
+    /// How fast fish swim up or down towards the water they prefer
+    pub const CLIMB_SPEED: f32 = 4.;
+}
+
+/// Reads `*.species.ron` files into [`Species`]
+#[derive(Default, TypePath)]
+pub struct SpeciesLoader;
+
+impl AssetLoader for SpeciesLoader {
+    type Asset = Species;
+    type Settings = ();
+    type Error = BevyError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Species, BevyError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["species.ron"]
+    }
+}
+
+/// Handles to every species named in `Config`, keyed by asset path. Keeps the
+/// species loaded for the whole game.
+#[derive(Resource, Default)]
+pub struct SpeciesLibrary(HashMap<String, Handle<Species>>);
+
+impl SpeciesLibrary {
+    pub fn get(&self, path: &str) -> Option<&Handle<Species>> {
+        self.0.get(path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Handle<Species>)> {
+        self.0.iter()
+    }
+}
+
+/// Starts loading all species used by the configured stages, and reports lures
+/// preferring species that aren't among them
+pub fn load_species(mut commands: Commands, config: Res<Config>, asset_server: Res<AssetServer>) {
+    let mut library = SpeciesLibrary::default();
+    let paths = config
+        .stages
+        .iter()
+        .chain(std::iter::once(&config.sample_stage))
+        .flat_map(|stage| {
+            let spawned = stage.spawn_table.iter().map(|entry| &entry.species);
+            spawned.chain(stage.boss.iter().map(|boss| &boss.species))
+        });
+    for path in paths {
+        library
+            .0
+            .entry(path.clone())
+            .or_insert_with(|| asset_server.load(path.clone()));
+    }
+    // Preferences are matched by path, so a typo would quietly lose the bonus
+    for lure in &config.lures {
+        for path in lure
+            .preferred
+            .iter()
+            .filter(|path| library.get(path).is_none())
+        {
+            error!(
+                "Lure {} prefers species {path}, which no stage spawns, so the preference does nothing",
+                lure.name
+            );
+        }
+    }
+    commands.insert_resource(library);
+}
+
+/// Reports species named in `Config` whose file failed to load. Fish of those
+/// species never spawn, so a mistyped path would otherwise go unnoticed.
+pub fn report_failed_species(mut failures: MessageReader<AssetLoadFailedEvent<Species>>) {
+    for failure in failures.read() {
+        error!(
+            "Species {} named in the config could not be loaded and won't spawn: {}",
+            failure.path, failure.error
+        );
+    }
+}
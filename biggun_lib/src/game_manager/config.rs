@@ -1,27 +1,99 @@
 //! Configuration data for the game. Should take the place of any would-be
 //! hard-coded constants.
+//!
+//! Configs are written in RON and loaded with [`Config::from_file`]. Any
+//! top-level field left out of the file falls back to [`Config::default`].
 
-use crate::environment::fish::Species;
+use crate::{
+    environment::{
+        boss::{BossConfig, BossMove, BossPhase},
+        current::CurrentConfig,
+        obstacle::{ObstacleConfig, ObstacleKind},
+    },
+    player::lure::{LureConfig, LureDepth},
+    utils::units::Inches,
+};
 use bevy::prelude::*;
-use std::time::Duration;
+use serde::{Deserialize, Deserializer, de};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 /// Configuration for each stage or level
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct StageConfig {
     /// The score needed to pass this stage
     pub target_score: u32,
     /// The maximum y distance from `water_level` where fish can spawn.
-    /// If greater than a species minimum depth, do not spawn that fish.
+    /// If less than a species minimum depth, do not spawn that fish.
     /// Else clamp the species maximum depth to this value to distribute evenly.
     pub water_depth: f32,
     /// The number of fish allowed during this stage
     pub max_fish: u32,
     /// The types of fish that can spawn during this stage
-    pub species: Vec<Species>,
-    /// How much time to give the player to complete the stage
+    pub spawn_table: Vec<SpawnEntry>,
+    /// How much time to give the player to complete the stage. Written as
+    /// seconds in config files.
+    #[serde(deserialize_with = "deserialize_secs")]
     pub time: Duration,
+    /// Weeds, rocks and logs on the lakebed
+    #[serde(default)]
+    pub obstacles: Vec<ObstacleConfig>,
+    /// Bands of moving water
+    #[serde(default)]
+    pub currents: Vec<CurrentConfig>,
+    /// A unique fish that shows up once during the stage
+    #[serde(default)]
+    pub boss: Option<BossConfig>,
+}
+
+/// A weighted entry in a stage's spawn table
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnEntry {
+    /// Path to a `*.species.ron` file from `assets/`
+    pub species: String,
+    /// Chance of being picked relative to the other entries in the table
+    #[serde(default = "SpawnEntry::default_weight")]
+    pub weight: f32,
+    /// Replaces the species depth range for this stage
+    #[serde(default)]
+    pub depth: Option<DepthBand>,
+    /// How many fish of this species may be alive at once
+    #[serde(default)]
+    pub max_concurrent: Option<u32>,
+}
+
+impl SpawnEntry {
+    fn default_weight() -> f32 {
+        1.
+    }
+
+    /// An entry with a weight of 1 and no extra restrictions
+    pub fn new(species: &str) -> SpawnEntry {
+        SpawnEntry {
+            species: species.to_string(),
+            weight: SpawnEntry::default_weight(),
+            depth: None,
+            max_concurrent: None,
+        }
+    }
+}
+
+/// Range of depths below `water_level`, measured in inches
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DepthBand {
+    pub min: Inches,
+    pub max: Inches,
 }
 
 /// Configuration for game visuals (font sizes, colors, etc.)
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct VisualConfig {
     pub score_font_size: f32,
     pub score_padding: f32,
@@ -30,9 +102,25 @@ pub struct VisualConfig {
     pub info_font_size: f32,
     /// Width of the fishing line in world scale
     pub line_width: f32,
+    /// Width of the line tension meter in the HUD
+    pub tension_meter_width: f32,
 }
 
-#[derive(Resource)]
+impl Default for VisualConfig {
+    fn default() -> Self {
+        Self {
+            score_font_size: 32.,
+            score_padding: 5.,
+            blinds_opacity: 0.8,
+            info_font_size: 18.,
+            line_width: 0.5,
+            tension_meter_width: 120.,
+        }
+    }
+}
+
+#[derive(Resource, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
     /// How far out the boundaries are from the center of the screen (x=0)
     pub game_width: f32,
@@ -46,8 +134,23 @@ pub struct Config {
     pub sample_stage: StageConfig,
     /// Font sizes, colors, etc.
     pub visuals: VisualConfig,
+    /// How long the stage clear screen is shown before the next stage.
+    /// Written as seconds in config files.
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub stage_clear_time: Duration,
+    /// How many runs are kept in the high score table
+    pub high_score_count: usize,
+    /// Lures players choose between, the first being on every hook to start
+    /// with. With none, fish bite a bare hook on contact.
+    pub lures: Vec<LureConfig>,
 }
 
+/// Species used by the default config
+const BASS: &str = "species/bass.species.ron";
+const MINNOW: &str = "species/minnow.species.ron";
+const PIKE: &str = "species/pike.species.ron";
+const BUCKETMOUTH: &str = "species/bucketmouth.species.ron";
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -56,26 +159,309 @@ impl Default for Config {
             stages: vec![StageConfig {
                 target_score: 100,
                 water_depth: 150.,
-                max_fish: 5,
-                species: vec![Species::BASS],
+                max_fish: 12,
+                spawn_table: vec![
+                    SpawnEntry {
+                        max_concurrent: Some(5),
+                        ..SpawnEntry::new(BASS)
+                    },
+                    SpawnEntry {
+                        weight: 0.5,
+                        max_concurrent: Some(6),
+                        ..SpawnEntry::new(MINNOW)
+                    },
+                    SpawnEntry {
+                        weight: 0.2,
+                        max_concurrent: Some(1),
+                        ..SpawnEntry::new(PIKE)
+                    },
+                ],
                 time: Duration::from_secs_f32(60.),
+                obstacles: vec![
+                    ObstacleConfig {
+                        kind: ObstacleKind::Weeds,
+                        x: -110.,
+                        width: 30.,
+                        height: 60.,
+                    },
+                    ObstacleConfig {
+                        kind: ObstacleKind::Rock,
+                        x: 45.,
+                        width: 24.,
+                        height: 20.,
+                    },
+                    ObstacleConfig {
+                        kind: ObstacleKind::Log,
+                        x: 120.,
+                        width: 50.,
+                        height: 12.,
+                    },
+                ],
+                currents: vec![
+                    CurrentConfig {
+                        top: 0.,
+                        bottom: 40.,
+                        x: None,
+                        flow: Vec2::new(6., 0.),
+                    },
+                    CurrentConfig {
+                        top: 100.,
+                        bottom: 150.,
+                        x: Some((-60., 60.)),
+                        flow: Vec2::new(-14., 0.),
+                    },
+                ],
+                boss: Some(BossConfig {
+                    name: "OLD BUCKETMOUTH".to_string(),
+                    species: BUCKETMOUTH.to_string(),
+                    appears_after: 20.,
+                    reward: 1000,
+                    clears_stage: true,
+                    phases: vec![
+                        BossPhase {
+                            action: BossMove::Struggle,
+                            secs: 2.,
+                        },
+                        BossPhase {
+                            action: BossMove::Run(30.),
+                            secs: 1.5,
+                        },
+                        BossPhase {
+                            action: BossMove::Dive(25.),
+                            secs: 1.5,
+                        },
+                        BossPhase {
+                            action: BossMove::Jump(40.),
+                            secs: 1.,
+                        },
+                    ],
+                }),
             }],
             sample_stage: StageConfig {
                 target_score: 0,
                 water_depth: 200.,
                 max_fish: 20,
-                species: vec![Species::BASS],
+                spawn_table: vec![
+                    SpawnEntry::new(BASS),
+                    SpawnEntry {
+                        weight: 0.5,
+                        max_concurrent: Some(8),
+                        ..SpawnEntry::new(MINNOW)
+                    },
+                ],
                 time: Duration::from_secs_f32(0.),
+                obstacles: Vec::new(),
+                currents: Vec::new(),
+                boss: None,
             },
-            visuals: VisualConfig {
-                score_font_size: 32.,
-                score_padding: 5.,
-                blinds_opacity: 0.8,
-                info_font_size: 18.,
-                line_width: 0.5,
+            visuals: VisualConfig::default(),
+            stage_clear_time: Duration::from_secs_f32(3.),
+            high_score_count: 10,
+            lures: vec![
+                LureConfig {
+                    name: "WORM".to_string(),
+                    attraction_radius: 40.,
+                    appeal: 0.5,
+                    preferred: vec![BASS.to_string()],
+                    depth: LureDepth::Sinks,
+                    sink_rate: 8.,
+                },
+                LureConfig {
+                    name: "CRANKBAIT".to_string(),
+                    attraction_radius: 60.,
+                    appeal: 0.35,
+                    preferred: Vec::new(),
+                    depth: LureDepth::Suspends(40.),
+                    sink_rate: 20.,
+                },
+                LureConfig {
+                    name: "JIG".to_string(),
+                    attraction_radius: 30.,
+                    appeal: 0.45,
+                    preferred: vec![BASS.to_string()],
+                    depth: LureDepth::Sinks,
+                    sink_rate: 25.,
+                },
+                LureConfig {
+                    name: "LIVE MINNOW".to_string(),
+                    attraction_radius: 50.,
+                    appeal: 0.7,
+                    preferred: Vec::new(),
+                    depth: LureDepth::Suspends(20.),
+                    sink_rate: 4.,
+                },
+            ],
+        }
+    }
+}
+
+impl Config {
+    /// Reads a RON config file from `path`
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|error| ConfigError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        Config::from_ron(&source).map_err(|error| error.with_path(path))
+    }
+
+    /// Parses a config from a RON string. Errors have an empty path.
+    pub fn from_ron(source: &str) -> Result<Config, ConfigError> {
+        let parse_error = |field: String, message: String| ConfigError::Parse {
+            path: PathBuf::new(),
+            field,
+            message,
+        };
+
+        let mut deserializer = ron::Deserializer::from_str(source)
+            .map_err(|e| parse_error(String::new(), e.to_string()))?;
+        let config: Config = serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+            let field = e.path().to_string();
+            let position = deserializer.span_error(e.into_inner()).to_string();
+            parse_error(field, position)
+        })?;
+        deserializer
+            .end()
+            .map_err(|e| parse_error(String::new(), deserializer.span_error(e).to_string()))?;
+
+        Ok(config)
+    }
+}
+
+/// Describes why a config file could not be used
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file could not be read
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    /// The file was read, but `field` is missing or has a bad value
+    Parse {
+        path: PathBuf,
+        field: String,
+        message: String,
+    },
+}
+
+impl ConfigError {
+    fn with_path(self, new_path: &Path) -> ConfigError {
+        match self {
+            Self::Io { error, .. } => Self::Io {
+                path: new_path.to_path_buf(),
+                error,
+            },
+            Self::Parse { field, message, .. } => Self::Parse {
+                path: new_path.to_path_buf(),
+                field,
+                message,
             },
         }
     }
 }
 
-// TODO: Configurable... config. Use toml?
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, error } => {
+                write!(f, "could not read config {}: {error}", path.display())
+            }
+            Self::Parse {
+                path,
+                field,
+                message,
+            } => {
+                // `serde_path_to_error` uses "." for the root
+                if field.is_empty() || field == "." {
+                    write!(f, "{}: {message}", path.display())
+                } else {
+                    write!(f, "{}: bad field `{field}`: {message}", path.display())
+                }
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Reads a duration written as a number of seconds
+fn deserialize_secs<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let secs = f32::deserialize(deserializer)?;
+    Duration::try_from_secs_f32(secs).map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A config with two stages, the second ending with `last_field`
+    fn second_stage_with(last_field: &str) -> String {
+        format!(
+            "(stages: [
+                (target_score: 10, water_depth: 100., max_fish: 3, spawn_table: [], time: 30.),
+                (target_score: 20, water_depth: 100., spawn_table: [], time: 30., {last_field}),
+            ])"
+        )
+    }
+
+    /// Writes `source` to a file named `name` and loads it
+    fn load(name: &str, source: &str) -> Result<Config, ConfigError> {
+        let path = std::env::temp_dir().join(format!("{}-{name}", std::process::id()));
+        std::fs::write(&path, source).unwrap();
+        let result = Config::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn fills_left_out_fields_from_defaults() {
+        let config = Config::from_ron("()").ok().unwrap();
+        let defaults = Config::default();
+        assert_eq!(config.stages.len(), defaults.stages.len());
+        assert_eq!(config.game_width, defaults.game_width);
+        assert_eq!(config.lures.len(), defaults.lures.len());
+    }
+
+    #[test]
+    fn reads_stages() {
+        let config = Config::from_ron(&second_stage_with("max_fish: 5"))
+            .ok()
+            .unwrap();
+        assert_eq!(config.stages.len(), 2);
+        assert_eq!(config.stages[1].target_score, 20);
+        assert_eq!(config.stages[1].max_fish, 5);
+    }
+
+    #[test]
+    fn names_the_file_and_field_of_a_mistyped_value() {
+        let error = load("mistyped.ron", &second_stage_with("max_fish: \"lots\""))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("mistyped.ron"), "{error}");
+        assert!(error.contains("`stages[1].max_fish`"), "{error}");
+    }
+
+    #[test]
+    fn names_the_file_and_stage_of_a_misspelled_field() {
+        let error = load("misspelled.ron", &second_stage_with("max_fsh: 5"))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("misspelled.ron"), "{error}");
+        assert!(error.contains("`stages[1]"), "{error}");
+        assert!(error.contains("max_fsh"), "{error}");
+    }
+
+    #[test]
+    fn names_a_missing_file() {
+        let path = std::env::temp_dir().join("biggun-no-such-config.ron");
+        let error = Config::from_file(&path).err().unwrap();
+        assert!(matches!(error, ConfigError::Io { .. }));
+        assert!(error.to_string().contains("biggun-no-such-config.ron"));
+    }
+}
@@ -0,0 +1,398 @@
+//! Action mapping for keyboard and gamepad. Gameplay and menus ask for an
+//! [`Action`] through [`ActionInput`] instead of reading keys directly, so
+//! bindings can be changed in the controls menu. Bindings are saved as RON in
+//! the platform config directory.
+//!
+//! Each player has their own keys for fishing, and plays with the gamepad
+//! matching their index in the order gamepads were connected. Menus take
+//! input from any gamepad.
+
+use crate::player::Player;
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Something a player can do. Fishing actions are done by the player with the
+/// given index.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    MoveLeft(usize),
+    MoveRight(usize),
+    Reel(usize),
+    Confirm,
+    Pause,
+    MenuUp,
+    MenuDown,
+    /// Opens the controls menu from the main menu
+    OpenControls,
+    /// Toggles drawing the current field
+    ShowCurrents,
+    /// Takes a player out of the next run on the main menu
+    FewerPlayers,
+    /// Adds a player to the next run on the main menu
+    MorePlayers,
+    /// Switches the next run between co-op and competitive on the main menu
+    SwitchMode,
+}
+
+impl Action {
+    /// Actions that aren't tied to a player
+    const SHARED: [Action; 9] = [
+        Self::Confirm,
+        Self::Pause,
+        Self::MenuUp,
+        Self::MenuDown,
+        Self::OpenControls,
+        Self::ShowCurrents,
+        Self::FewerPlayers,
+        Self::MorePlayers,
+        Self::SwitchMode,
+    ];
+
+    /// Every action of every player
+    pub fn all() -> impl Iterator<Item = Action> {
+        (0..Player::MAX)
+            .flat_map(|player| {
+                [
+                    Self::MoveLeft(player),
+                    Self::MoveRight(player),
+                    Self::Reel(player),
+                ]
+            })
+            .chain(Self::SHARED)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::MoveLeft(_) => "MOVE LEFT",
+            Self::MoveRight(_) => "MOVE RIGHT",
+            Self::Reel(_) => "REEL",
+            Self::Confirm => "CONFIRM",
+            Self::Pause => "PAUSE",
+            Self::MenuUp => "MENU UP",
+            Self::MenuDown => "MENU DOWN",
+            Self::OpenControls => "CONTROLS",
+            Self::ShowCurrents => "SHOW CURRENTS",
+            Self::FewerPlayers => "FEWER PLAYERS",
+            Self::MorePlayers => "MORE PLAYERS",
+            Self::SwitchMode => "SWITCH MODE",
+        }
+    }
+
+    /// Index of the player doing the action, if only one player can
+    pub fn player(&self) -> Option<usize> {
+        match self {
+            Self::MoveLeft(player) | Self::MoveRight(player) | Self::Reel(player) => Some(*player),
+            Self::Confirm
+            | Self::Pause
+            | Self::MenuUp
+            | Self::MenuDown
+            | Self::OpenControls
+            | Self::ShowCurrents
+            | Self::FewerPlayers
+            | Self::MorePlayers
+            | Self::SwitchMode => None,
+        }
+    }
+
+    /// True if both actions can be read from the same gamepad. Only
+    /// different players' fishing actions can't.
+    fn shares_gamepad(&self, other: Action) -> bool {
+        match (self.player(), other.player()) {
+            (Some(player), Some(other)) => player == other,
+            _ => true,
+        }
+    }
+}
+
+/// The keyboard key and gamepad button bound to an action
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Binding {
+    pub key: KeyCode,
+    pub button: GamepadButton,
+}
+
+impl Binding {
+    const fn new(key: KeyCode, button: GamepadButton) -> Binding {
+        Binding { key, button }
+    }
+
+    /// Name of the bound key for prompts, e.g. `ENTER`
+    pub fn key_label(&self) -> String {
+        let key = format!("{:?}", self.key);
+        key.strip_prefix("Key")
+            .or_else(|| key.strip_prefix("Digit"))
+            .unwrap_or(&key)
+            .to_uppercase()
+    }
+
+    /// Formats the binding for display, e.g. `A / DPadLeft`
+    pub fn label(&self) -> String {
+        format!("{} / {:?}", self.key_label(), self.button)
+    }
+}
+
+/// Bindings for one player's fishing actions
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PlayerBindings {
+    pub move_left: Binding,
+    pub move_right: Binding,
+    pub reel: Binding,
+}
+
+impl PlayerBindings {
+    const fn new(left: KeyCode, right: KeyCode, reel: KeyCode) -> PlayerBindings {
+        // Every player has a gamepad of their own, so the buttons can match
+        PlayerBindings {
+            move_left: Binding::new(left, GamepadButton::DPadLeft),
+            move_right: Binding::new(right, GamepadButton::DPadRight),
+            reel: Binding::new(reel, GamepadButton::RightTrigger2),
+        }
+    }
+}
+
+/// Bindings for every action
+#[derive(Resource, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Controls {
+    /// Indexed by `Player::index`
+    pub players: [PlayerBindings; Player::MAX],
+    pub confirm: Binding,
+    pub pause: Binding,
+    pub menu_up: Binding,
+    pub menu_down: Binding,
+    pub open_controls: Binding,
+    pub show_currents: Binding,
+    pub fewer_players: Binding,
+    pub more_players: Binding,
+    pub switch_mode: Binding,
+    /// How far the left stick must be pushed before it moves the hook
+    pub dead_zone: f32,
+    /// Where the bindings are saved. `None` if they aren't.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Controls {
+            players: [
+                PlayerBindings::new(KeyCode::KeyA, KeyCode::KeyD, KeyCode::Space),
+                PlayerBindings::new(KeyCode::ArrowLeft, KeyCode::ArrowRight, KeyCode::ArrowUp),
+                PlayerBindings::new(KeyCode::KeyJ, KeyCode::KeyL, KeyCode::KeyI),
+                PlayerBindings::new(KeyCode::Numpad4, KeyCode::Numpad6, KeyCode::Numpad8),
+            ],
+            confirm: Binding::new(KeyCode::Enter, GamepadButton::South),
+            pause: Binding::new(KeyCode::Escape, GamepadButton::Start),
+            menu_up: Binding::new(KeyCode::KeyW, GamepadButton::DPadUp),
+            menu_down: Binding::new(KeyCode::KeyS, GamepadButton::DPadDown),
+            open_controls: Binding::new(KeyCode::KeyC, GamepadButton::Select),
+            show_currents: Binding::new(KeyCode::F3, GamepadButton::LeftThumb),
+            fewer_players: Binding::new(KeyCode::KeyQ, GamepadButton::LeftTrigger),
+            more_players: Binding::new(KeyCode::KeyE, GamepadButton::RightTrigger),
+            switch_mode: Binding::new(KeyCode::KeyM, GamepadButton::North),
+            dead_zone: 0.15,
+            path: None,
+        }
+    }
+}
+
+impl Controls {
+    pub fn binding(&self, action: Action) -> &Binding {
+        match action {
+            Action::MoveLeft(player) => &self.players[player].move_left,
+            Action::MoveRight(player) => &self.players[player].move_right,
+            Action::Reel(player) => &self.players[player].reel,
+            Action::Confirm => &self.confirm,
+            Action::Pause => &self.pause,
+            Action::MenuUp => &self.menu_up,
+            Action::MenuDown => &self.menu_down,
+            Action::OpenControls => &self.open_controls,
+            Action::ShowCurrents => &self.show_currents,
+            Action::FewerPlayers => &self.fewer_players,
+            Action::MorePlayers => &self.more_players,
+            Action::SwitchMode => &self.switch_mode,
+        }
+    }
+
+    pub fn binding_mut(&mut self, action: Action) -> &mut Binding {
+        match action {
+            Action::MoveLeft(player) => &mut self.players[player].move_left,
+            Action::MoveRight(player) => &mut self.players[player].move_right,
+            Action::Reel(player) => &mut self.players[player].reel,
+            Action::Confirm => &mut self.confirm,
+            Action::Pause => &mut self.pause,
+            Action::MenuUp => &mut self.menu_up,
+            Action::MenuDown => &mut self.menu_down,
+            Action::OpenControls => &mut self.open_controls,
+            Action::ShowCurrents => &mut self.show_currents,
+            Action::FewerPlayers => &mut self.fewer_players,
+            Action::MorePlayers => &mut self.more_players,
+            Action::SwitchMode => &mut self.switch_mode,
+        }
+    }
+
+    /// The other action already using `binding`'s key or, on the same
+    /// gamepad, its button, if binding it to `action` would clash
+    pub fn conflict(&self, action: Action, binding: &Binding) -> Option<Action> {
+        Action::all()
+            .filter(|&other| other != action)
+            .find(|&other| {
+                let bound = self.binding(other);
+                bound.key == binding.key
+                    || (bound.button == binding.button && action.shares_gamepad(other))
+            })
+    }
+
+    /// Prompt shown once a run is over
+    pub fn restart_prompt(&self) -> String {
+        format!("press [{}] to restart", self.confirm.key_label())
+    }
+
+    fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("biggun").join("controls.ron"))
+    }
+
+    /// The default bindings, saved wherever these ones are
+    pub fn defaults(&self) -> Controls {
+        Controls {
+            path: self.path.clone(),
+            ..default()
+        }
+    }
+
+    /// Reads saved bindings, falling back to the defaults
+    pub fn load() -> Controls {
+        let path = Controls::default_path();
+        let controls = path
+            .as_ref()
+            .filter(|path| path.exists())
+            .map(|path| {
+                let read = std::fs::read_to_string(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|source| ron::from_str(&source).map_err(|e| e.to_string()));
+                read.unwrap_or_else(|e| {
+                    warn!("Could not read controls from {}: {e}", path.display());
+                    Controls::default()
+                })
+            })
+            .unwrap_or_default();
+        Controls { path, ..controls }
+    }
+
+    /// Writes the bindings to disk
+    pub fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())
+            .and_then(|text| {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+                }
+                std::fs::write(path, text).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            warn!("Could not save controls to {}: {e}", path.display());
+        }
+    }
+}
+
+/// Reads actions from the keyboard and connected gamepads
+#[derive(SystemParam)]
+pub struct ActionInput<'w, 's> {
+    controls: Res<'w, Controls>,
+    keyboard: Res<'w, ButtonInput<KeyCode>>,
+    gamepads: Query<'w, 's, (Entity, &'static Gamepad)>,
+}
+
+impl ActionInput<'_, '_> {
+    /// Gamepads that can do `action`: the player's own, or any for menus
+    fn gamepads_for(&self, action: Action) -> Vec<&Gamepad> {
+        let mut gamepads: Vec<_> = self.gamepads.iter().collect();
+        // Entities are allocated in the order gamepads connect
+        gamepads.sort_by_key(|(entity, _)| *entity);
+        let gamepads = gamepads.into_iter().map(|(_, gamepad)| gamepad);
+        match action.player() {
+            Some(player) => gamepads.skip(player).take(1).collect(),
+            None => gamepads.collect(),
+        }
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        let binding = self.controls.binding(action);
+        self.keyboard.pressed(binding.key)
+            || self
+                .gamepads_for(action)
+                .iter()
+                .any(|gamepad| gamepad.pressed(binding.button))
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        let binding = self.controls.binding(action);
+        self.keyboard.just_pressed(binding.key)
+            || self
+                .gamepads_for(action)
+                .iter()
+                .any(|gamepad| gamepad.just_pressed(binding.button))
+    }
+
+    /// How hard `player` steers their hook, from -1 (left) to 1 (right). The
+    /// left stick steers proportionally, buttons steer at full speed.
+    pub fn steer(&self, player: usize) -> f32 {
+        let buttons = f32::from(u8::from(self.pressed(Action::MoveRight(player))))
+            - f32::from(u8::from(self.pressed(Action::MoveLeft(player))));
+        let stick: f32 = self
+            .gamepads_for(Action::MoveRight(player))
+            .iter()
+            .map(|gamepad| gamepad.left_stick().x)
+            .filter(|x| x.abs() > self.controls.dead_zone)
+            .sum();
+        (buttons + stick).clamp(-1., 1.)
+    }
+
+    /// Like `just_pressed`, but ignoring the keyboard while it is used for
+    /// typing
+    pub fn button_just_pressed(&self, action: Action) -> bool {
+        let binding = self.controls.binding(action);
+        self.gamepads_for(action)
+            .iter()
+            .any(|gamepad| gamepad.just_pressed(binding.button))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_dont_conflict() {
+        let controls = Controls::default();
+        for action in Action::all() {
+            let conflict = controls.conflict(action, controls.binding(action));
+            assert!(conflict.is_none(), "{} clashes", action.label());
+        }
+    }
+
+    #[test]
+    fn players_share_buttons_but_not_keys() {
+        let controls = Controls::default();
+        let reel = *controls.binding(Action::Reel(0));
+        let taken_key = Binding {
+            key: reel.key,
+            ..reel
+        };
+        let own_button = Binding {
+            key: KeyCode::KeyZ,
+            ..*controls.binding(Action::Reel(1))
+        };
+        assert!(controls.conflict(Action::Reel(1), &taken_key) == Some(Action::Reel(0)));
+        assert!(controls.conflict(Action::Reel(1), &own_button).is_none());
+        let confirm_button = Binding {
+            key: KeyCode::KeyZ,
+            button: controls.confirm.button,
+        };
+        assert!(controls.conflict(Action::Reel(1), &confirm_button) == Some(Action::Confirm));
+    }
+}
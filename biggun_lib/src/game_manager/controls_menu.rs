@@ -0,0 +1,260 @@
+//! Menu for rebinding controls, opened from the main menu. Selecting an
+//! action waits for the next key or gamepad button pressed and binds it,
+//! unless another action already uses it. Pressing the pause action instead
+//! cancels. Each player in the session gets their own fishing actions.
+
+use super::{
+    config::Config,
+    controls::{Action, ActionInput, Controls},
+    session::Session,
+    state::AppState,
+};
+use bevy::prelude::*;
+
+/// A row of the controls menu
+#[derive(Component, Clone, Copy, PartialEq)]
+pub enum ControlsOption {
+    Rebind(Action),
+    ResetDefaults,
+    Back,
+}
+
+impl ControlsOption {
+    /// All options for a session of `players`, in the order they are shown
+    fn all(players: usize) -> Vec<ControlsOption> {
+        let fishing = (0..players).flat_map(|player| {
+            [
+                Self::Rebind(Action::MoveLeft(player)),
+                Self::Rebind(Action::MoveRight(player)),
+                Self::Rebind(Action::Reel(player)),
+            ]
+        });
+        fishing
+            .chain([
+                Self::Rebind(Action::Confirm),
+                Self::Rebind(Action::Pause),
+                Self::Rebind(Action::MenuUp),
+                Self::Rebind(Action::MenuDown),
+                Self::Rebind(Action::OpenControls),
+                Self::Rebind(Action::ShowCurrents),
+                Self::Rebind(Action::FewerPlayers),
+                Self::Rebind(Action::MorePlayers),
+                Self::Rebind(Action::SwitchMode),
+                Self::ResetDefaults,
+                Self::Back,
+            ])
+            .collect()
+    }
+
+    /// Describes the option, or what to press while `selection` is waiting
+    /// on it
+    fn label(&self, controls: &Controls, selection: &ControlsSelection, selected: bool) -> String {
+        let Self::Rebind(action) = self else {
+            return match self {
+                Self::ResetDefaults => "RESET DEFAULTS".to_string(),
+                _ => "BACK".to_string(),
+            };
+        };
+        let name = action_name(*action, selection.players);
+        if !(selected && selection.waiting) {
+            return format!("{name}: {}", controls.binding(*action).label());
+        }
+        let cancel = controls.pause.key_label();
+        match selection.conflict {
+            Some(other) => format!(
+                "{name}: taken by {}, press another or [{cancel}] to cancel",
+                action_name(other, selection.players)
+            ),
+            None => format!("{name}: press a key or button, [{cancel}] to cancel"),
+        }
+    }
+}
+
+/// Names an action, prefixing fishing actions with their player when there is
+/// more than one
+fn action_name(action: Action, players: usize) -> String {
+    match action.player() {
+        Some(player) if players > 1 => format!("P{} {}", player + 1, action.label()),
+        _ => action.label().to_string(),
+    }
+}
+
+/// Options of the open controls menu and which is highlighted
+#[derive(Resource, Default)]
+pub struct ControlsSelection {
+    options: Vec<ControlsOption>,
+    /// Index into `options` of the highlighted option
+    index: usize,
+    /// Players in the session the menu was opened for
+    players: usize,
+    /// Waiting for a key or button to bind to the highlighted action
+    waiting: bool,
+    /// The action using the last key or button pressed while waiting
+    conflict: Option<Action>,
+    /// A binding was captured this frame, so its press shouldn't also be
+    /// taken as menu input
+    captured: bool,
+}
+
+/// Opens the controls menu with the open controls action
+pub fn handle_open_key(actions: ActionInput, mut next_state: ResMut<NextState<AppState>>) {
+    if actions.just_pressed(Action::OpenControls) {
+        next_state.set(AppState::Controls);
+    }
+}
+
+/// Spawns the controls menu
+pub fn open_controls_menu(
+    mut commands: Commands,
+    mut selection: ResMut<ControlsSelection>,
+    session: Res<Session>,
+    config: Res<Config>,
+    asset_server: Res<AssetServer>,
+) {
+    *selection = ControlsSelection {
+        options: ControlsOption::all(session.players),
+        players: session.players,
+        ..default()
+    };
+
+    let font = asset_server.load("kodemono.ttf");
+    let visuals = &config.visuals;
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: percent(100),
+                height: percent(100),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: px(visuals.score_padding),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0., 0., 0., visuals.blinds_opacity)),
+            DespawnOnExit(AppState::Controls),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("CONTROLS"),
+                TextFont::from(font.clone()).with_font_size(visuals.score_font_size),
+                TextColor(Color::WHITE),
+                TextLayout::new_with_justify(Justify::Center),
+            ));
+            for &option in &selection.options {
+                parent.spawn((
+                    Text::default(),
+                    TextFont::from(font.clone()).with_font_size(visuals.info_font_size),
+                    TextColor(Color::WHITE),
+                    TextLayout::new_with_justify(Justify::Center),
+                    option,
+                ));
+            }
+        });
+}
+
+/// Binds the next key or button pressed to the highlighted action, or stops
+/// waiting if it was the pause action's
+pub fn capture_binding(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut controls: ResMut<Controls>,
+    mut selection: ResMut<ControlsSelection>,
+) {
+    if !selection.waiting {
+        return;
+    }
+    let ControlsOption::Rebind(action) = selection.options[selection.index] else {
+        selection.waiting = false;
+        return;
+    };
+
+    let mut binding = *controls.binding(action);
+    let cancel = if let Some(&key) = keyboard_input.get_just_pressed().next() {
+        binding.key = key;
+        key == controls.pause.key
+    } else if let Some(&button) = gamepads
+        .iter()
+        .find_map(|gamepad| gamepad.get_just_pressed().next())
+    {
+        binding.button = button;
+        button == controls.pause.button
+    } else {
+        return;
+    };
+    if !cancel {
+        selection.conflict = controls.conflict(action, &binding);
+        if selection.conflict.is_some() {
+            return;
+        }
+        *controls.binding_mut(action) = binding;
+        controls.save();
+    }
+    selection.waiting = false;
+    selection.conflict = None;
+    selection.captured = true;
+}
+
+/// Moves the selection up and down and picks an option with the confirm
+/// action. The pause action goes back to the main menu.
+pub fn handle_menu_input(
+    actions: ActionInput,
+    mut selection: ResMut<ControlsSelection>,
+    controls: Res<Controls>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut commands: Commands,
+) {
+    if selection.waiting {
+        return;
+    }
+    if selection.captured {
+        selection.captured = false;
+        return;
+    }
+
+    let count = selection.options.len();
+    if actions.just_pressed(Action::MenuUp) {
+        selection.index = (selection.index + count - 1) % count;
+    }
+    if actions.just_pressed(Action::MenuDown) {
+        selection.index = (selection.index + 1) % count;
+    }
+
+    if actions.just_pressed(Action::Pause) {
+        next_state.set(AppState::MainMenu);
+        return;
+    }
+    if !actions.just_pressed(Action::Confirm) {
+        return;
+    }
+    match selection.options[selection.index] {
+        ControlsOption::Rebind(_) => selection.waiting = true,
+        ControlsOption::ResetDefaults => {
+            let controls = controls.defaults();
+            controls.save();
+            commands.insert_resource(controls);
+        }
+        ControlsOption::Back => next_state.set(AppState::MainMenu),
+    }
+}
+
+/// Shows each option's binding and marks the selected option
+pub fn update_options(
+    selection: Res<ControlsSelection>,
+    controls: Res<Controls>,
+    options: Query<(&ControlsOption, &mut Text)>,
+) {
+    if !selection.is_changed() && !controls.is_changed() {
+        return;
+    }
+    let selected = selection.options[selection.index];
+    for (option, mut text) in options {
+        let label = option.label(&controls, &selection, *option == selected);
+        text.0 = if *option == selected {
+            format!("> {label} <")
+        } else {
+            label
+        };
+    }
+}
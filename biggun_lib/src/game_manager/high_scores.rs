@@ -0,0 +1,425 @@
+//! Local high score table, saved as RON in the platform data directory
+
+use super::{
+    config::Config,
+    controls::{Action, ActionInput, Controls},
+    session::Session,
+    state::GameState,
+};
+use crate::utils::units::Ounces;
+use bevy::{
+    input::keyboard::{Key, KeyboardInput},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Bumped whenever the save format changes in a way `serde(default)` can't
+/// cover. Older versions are still read; unknown fields are ignored.
+const SAVE_VERSION: u32 = 1;
+
+/// Longest name that can be entered for a record
+const MAX_NAME_LEN: usize = 8;
+
+/// Characters a gamepad can pick from, in order
+const NAME_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+/// A single finished run
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct HighScoreEntry {
+    pub name: String,
+    pub score: u32,
+    /// Counting from 1
+    pub stage: usize,
+    /// Weight of the heaviest fish landed during the run
+    pub biggest_fish: Ounces,
+    /// Formatted as YYYY-MM-DD
+    pub date: String,
+}
+
+/// Layout of the save file
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct HighScoreFile {
+    version: u32,
+    entries: Vec<HighScoreEntry>,
+}
+
+/// The best runs, highest score first
+#[derive(Resource, Default)]
+pub struct HighScores {
+    pub entries: Vec<HighScoreEntry>,
+    /// Where the table is saved. `None` if there is no data directory.
+    path: Option<PathBuf>,
+}
+
+impl HighScores {
+    fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("biggun").join("high_scores.ron"))
+    }
+
+    /// Reads the table from disk, starting empty if it can't be read
+    pub fn load() -> HighScores {
+        let path = HighScores::default_path();
+        let entries = path
+            .as_ref()
+            .filter(|path| path.exists())
+            .and_then(|path| {
+                let read = std::fs::read_to_string(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|source| {
+                        ron::from_str::<HighScoreFile>(&source).map_err(|e| e.to_string())
+                    });
+                match read {
+                    Ok(file) => {
+                        if file.version > SAVE_VERSION {
+                            warn!(
+                                "High scores in {} are from a newer version ({})",
+                                path.display(),
+                                file.version
+                            );
+                        }
+                        Some(file.entries)
+                    }
+                    Err(e) => {
+                        warn!("Could not read high scores from {}: {e}", path.display());
+                        None
+                    }
+                }
+            })
+            .unwrap_or_default();
+        HighScores { entries, path }
+    }
+
+    /// Writes the table to disk
+    pub fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let file = HighScoreFile {
+            version: SAVE_VERSION,
+            entries: self.entries.clone(),
+        };
+        let result = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())
+            .and_then(|text| {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+                }
+                std::fs::write(path, text).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            warn!("Could not save high scores to {}: {e}", path.display());
+        }
+    }
+
+    /// True if `score` would make it onto a table of `max_entries`
+    pub fn qualifies(&self, score: u32, max_entries: usize) -> bool {
+        score > 0
+            && (self.entries.len() < max_entries
+                || self.entries.iter().any(|entry| score > entry.score))
+    }
+
+    /// Adds an entry in order, dropping whatever falls past `max_entries`
+    pub fn insert(&mut self, entry: HighScoreEntry, max_entries: usize) {
+        let index = self
+            .entries
+            .iter()
+            .position(|other| entry.score > other.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(index, entry);
+        self.entries.truncate(max_entries);
+    }
+
+    /// Formats the table for display
+    pub fn table_text(&self) -> String {
+        let mut text = String::from("HIGH SCORES");
+        if self.entries.is_empty() {
+            text.push_str("\n\n---");
+        }
+        for (i, entry) in self.entries.iter().enumerate() {
+            let (lbs, ozs) = entry.biggest_fish.clone().lbs_ozs();
+            text.push_str(&format!(
+                "\n{:>2}. {:<8} {:08}  STAGE {:<2} {:>2}LB {:>2}OZ  {}",
+                i + 1,
+                entry.name,
+                entry.score,
+                entry.stage,
+                lbs,
+                ozs,
+                entry.date
+            ));
+        }
+        text
+    }
+}
+
+/// Text showing the high score table. Kept up to date with `HighScores`.
+#[derive(Component)]
+#[require(Text)]
+pub struct HighScoreDisplay;
+
+/// Prompt for the name of a new record. Removed once the name is confirmed.
+#[derive(Component, Default)]
+#[require(Text)]
+pub struct NameEntry {
+    name: String,
+}
+
+impl NameEntry {
+    pub fn prompt(&self) -> String {
+        format!("NEW HIGH SCORE! ENTER NAME: {}_", self.name)
+    }
+
+    fn push(&mut self, c: char) {
+        if self.name.len() < MAX_NAME_LEN {
+            self.name.push(c.to_ascii_uppercase());
+        }
+    }
+
+    /// Steps the last character `step` places through `NAME_CHARS`,
+    /// starting the name if it is empty
+    fn cycle(&mut self, step: isize) {
+        let Some(last) = self.name.pop() else {
+            self.push('A');
+            return;
+        };
+        let index = NAME_CHARS
+            .iter()
+            .position(|&c| char::from(c) == last)
+            .unwrap_or(0) as isize;
+        let index = (index + step).rem_euclid(NAME_CHARS.len() as isize) as usize;
+        self.push(char::from(NAME_CHARS[index]));
+    }
+}
+
+/// Types the record holder's name and saves the record with the confirm
+/// action. Gamepads pick each character with the menu actions, moving right
+/// to start the next one and left to delete. Keys pressed before the prompt
+/// appeared are ignored.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_name_entry(
+    mut keyboard_input: MessageReader<KeyboardInput>,
+    actions: ActionInput,
+    prompt: Single<(Entity, &mut NameEntry, &mut Text)>,
+    mut high_scores: ResMut<HighScores>,
+    state: Res<GameState>,
+    config: Res<Config>,
+    controls: Res<Controls>,
+    session: Res<Session>,
+    mut commands: Commands,
+) {
+    let (entity, mut entry, mut text) = prompt.into_inner();
+    if entry.is_added() {
+        keyboard_input.clear();
+        text.0 = entry.prompt();
+        return;
+    }
+    if actions.just_pressed(Action::Confirm) {
+        let name = if entry.name.is_empty() {
+            "???".to_string()
+        } else {
+            entry.name.clone()
+        };
+        high_scores.insert(
+            HighScoreEntry {
+                name,
+                score: state.final_score(),
+                stage: state.stage_number(),
+                biggest_fish: state.biggest_fish.clone(),
+                date: today(),
+            },
+            config.high_score_count,
+        );
+        high_scores.save();
+        commands.entity(entity).remove::<NameEntry>();
+        text.0 = controls.restart_prompt();
+        return;
+    }
+
+    for input in keyboard_input.read() {
+        if !input.state.is_pressed() {
+            continue;
+        }
+        match &input.logical_key {
+            Key::Backspace => {
+                entry.name.pop();
+            }
+            Key::Character(chars) => {
+                for c in chars.chars().filter(char::is_ascii_alphanumeric) {
+                    entry.push(c);
+                }
+            }
+            _ => {}
+        }
+    }
+    // The keyboard is busy typing, so only gamepads pick characters
+    if actions.button_just_pressed(Action::MenuUp) {
+        entry.cycle(1);
+    }
+    if actions.button_just_pressed(Action::MenuDown) {
+        entry.cycle(-1);
+    }
+    // Any player's gamepad can add or remove characters
+    let any_player = |action: fn(usize) -> Action| {
+        (0..session.players).any(|player| actions.button_just_pressed(action(player)))
+    };
+    if any_player(Action::MoveRight) && !entry.name.is_empty() {
+        entry.push('A');
+    }
+    if any_player(Action::MoveLeft) {
+        entry.name.pop();
+    }
+    text.0 = entry.prompt();
+}
+
+/// True while a record holder is typing their name
+pub fn entering_name(prompt: Query<(), With<NameEntry>>) -> bool {
+    !prompt.is_empty()
+}
+
+pub fn update_display(
+    high_scores: Res<HighScores>,
+    displays: Query<&mut Text, With<HighScoreDisplay>>,
+) {
+    if !high_scores.is_changed() {
+        return;
+    }
+    for mut text in displays {
+        text.0 = high_scores.table_text();
+    }
+}
+
+/// Today's date in UTC as YYYY-MM-DD
+fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    civil_date((secs / 86_400) as i64)
+}
+
+/// Days since 1970-01-01 to a civil date as YYYY-MM-DD. See Howard
+/// Hinnant's `civil_from_days`.
+fn civil_date(days: i64) -> String {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, score: u32) -> HighScoreEntry {
+        HighScoreEntry {
+            name: name.to_string(),
+            score,
+            ..default()
+        }
+    }
+
+    fn names(high_scores: &HighScores) -> Vec<&str> {
+        high_scores
+            .entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn reads_files_without_a_version() {
+        let file: HighScoreFile = ron::from_str("(entries: [(name: \"OLD\", score: 10)])").unwrap();
+        assert_eq!(file.version, 0);
+        assert_eq!(file.entries[0].name, "OLD");
+        assert_eq!(file.entries[0].score, 10);
+    }
+
+    #[test]
+    fn reads_newer_files_ignoring_unknown_fields() {
+        let file: HighScoreFile = ron::from_str(
+            "(version: 99, entries: [(name: \"NEW\", score: 5, combo: 3)], extra: true)",
+        )
+        .unwrap();
+        assert_eq!(file.version, 99);
+        assert_eq!(file.entries[0].name, "NEW");
+    }
+
+    #[test]
+    fn saves_with_the_current_version() {
+        let file = HighScoreFile {
+            version: SAVE_VERSION,
+            entries: vec![entry("A", 1)],
+        };
+        let text = ron::to_string(&file).unwrap();
+        let file: HighScoreFile = ron::from_str(&text).unwrap();
+        assert_eq!(file.version, SAVE_VERSION);
+        assert_eq!(file.entries.len(), 1);
+    }
+
+    #[test]
+    fn qualifies_onto_a_short_or_beaten_table() {
+        let mut high_scores = HighScores::default();
+        assert!(!high_scores.qualifies(0, 2));
+        assert!(high_scores.qualifies(1, 2));
+        high_scores.insert(entry("A", 10), 2);
+        high_scores.insert(entry("B", 20), 2);
+        assert!(!high_scores.qualifies(10, 2));
+        assert!(high_scores.qualifies(11, 2));
+    }
+
+    #[test]
+    fn inserts_highest_first_and_truncates() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(entry("A", 10), 3);
+        high_scores.insert(entry("B", 30), 3);
+        high_scores.insert(entry("C", 20), 3);
+        assert_eq!(names(&high_scores), ["B", "C", "A"]);
+        high_scores.insert(entry("D", 25), 3);
+        assert_eq!(names(&high_scores), ["B", "D", "C"]);
+        high_scores.insert(entry("E", 5), 3);
+        assert_eq!(names(&high_scores), ["B", "D", "C"]);
+    }
+
+    #[test]
+    fn ties_go_below_earlier_records() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(entry("A", 10), 3);
+        high_scores.insert(entry("B", 10), 3);
+        assert_eq!(names(&high_scores), ["A", "B"]);
+    }
+
+    #[test]
+    fn gamepad_cycles_through_name_chars() {
+        let mut entry = NameEntry::default();
+        entry.cycle(1);
+        assert_eq!(entry.name, "A");
+        entry.cycle(-1);
+        assert_eq!(entry.name, "9");
+        entry.cycle(1);
+        entry.cycle(1);
+        assert_eq!(entry.name, "B");
+    }
+
+    #[test]
+    fn civil_date_from_days() {
+        assert_eq!(civil_date(0), "1970-01-01");
+        assert_eq!(civil_date(-1), "1969-12-31");
+        assert_eq!(civil_date(59), "1970-03-01");
+        // Leap days, including the century exceptions
+        assert_eq!(civil_date(11_016), "2000-02-29");
+        assert_eq!(civil_date(19_782), "2024-02-29");
+        assert_eq!(civil_date(47_541), "2100-03-01");
+        assert_eq!(civil_date(20_818), "2026-12-31");
+    }
+}
@@ -1,19 +1,154 @@
 use bevy::prelude::*;
 
 pub(crate) mod config;
+pub(crate) mod controls;
+pub(crate) mod controls_menu;
+pub(crate) mod high_scores;
+pub(crate) mod pause;
+pub(crate) mod replay;
+pub(crate) mod rng;
 pub(crate) mod scenes;
+pub(crate) mod session;
 pub(crate) mod state;
 
 /// Game management for biggun. Scene transition, state management, etc.
 pub struct BiggunGameManagerPlugin;
 
+/// Whether high scores and controls are read from and saved to disk. Turned
+/// off by the headless sim so that it leaves the player's files alone.
+#[derive(Resource)]
+pub struct Persistence {
+    pub enabled: bool,
+}
+
+impl Default for Persistence {
+    fn default() -> Self {
+        Persistence { enabled: true }
+    }
+}
+
+/// Order of gameplay within a `FixedUpdate` tick. Gameplay only advances in
+/// fixed ticks and in this order so that runs can be replayed exactly.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TickSet {
+    /// Reading this tick's `RunInput`
+    Input,
+    /// The player acting on the input
+    Control,
+    /// Fish spawning, moving and struggling
+    Fish,
+    Physics,
+    /// Scoring, the countdown and stage progress
+    Rules,
+}
+
 impl Plugin for BiggunGameManagerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_observer(state::stage_transition)
-            .add_systems(Update, (state::CountdownTimer::tick, state::handle_input))
-            .add_systems(Startup, scenes::load_main_menu)
-            .add_observer(scenes::load_game)
-            .add_observer(scenes::game_over_screen)
-            .add_observer(scenes::on_scene_transition);
+        // Replaced by the saved files in `finish` if persistence is on
+        app.init_resource::<Persistence>()
+            .init_resource::<high_scores::HighScores>()
+            .init_resource::<controls::Controls>()
+            .init_resource::<rng::GameRng>()
+            .init_resource::<session::Session>()
+            .init_resource::<replay::RunInput>()
+            .configure_sets(
+                FixedUpdate,
+                (
+                    TickSet::Input,
+                    TickSet::Control,
+                    TickSet::Fish,
+                    TickSet::Physics,
+                    TickSet::Rules,
+                )
+                    .chain(),
+            )
+            .init_state::<state::AppState>()
+            .add_computed_state::<state::InGame>()
+            .add_observer(state::stage_transition)
+            .add_observer(state::on_game_over)
+            .add_systems(
+                FixedUpdate,
+                (
+                    (replay::read_input, replay::restart_stage)
+                        .chain()
+                        .in_set(TickSet::Input),
+                    (state::CountdownTimer::tick, state::evaluate_stage)
+                        .chain()
+                        .in_set(TickSet::Rules),
+                )
+                    .run_if(in_state(state::AppState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (
+                    state::handle_input.run_if(in_state(state::AppState::MainMenu).or(
+                        in_state(state::AppState::GameOver).and(not(high_scores::entering_name)),
+                    )),
+                    high_scores::handle_name_entry.run_if(in_state(state::AppState::GameOver)),
+                    high_scores::update_display,
+                )
+                    .chain(),
+            )
+            .add_systems(Startup, scenes::setup_world)
+            .add_systems(OnEnter(state::AppState::MainMenu), scenes::load_main_menu)
+            .add_systems(
+                OnEnter(state::InGame),
+                (scenes::load_game, replay::start_run).chain(),
+            )
+            .add_systems(
+                OnExit(state::InGame),
+                replay::save_recording.run_if(resource_exists::<replay::Recorder>),
+            )
+            .add_systems(OnEnter(state::AppState::GameOver), scenes::game_over_screen)
+            .add_observer(scenes::stage_clear_screen)
+            .init_resource::<pause::PauseSelection>()
+            .add_systems(
+                OnEnter(state::AppState::Paused),
+                (pause::freeze_time, pause::open_pause_menu),
+            )
+            .add_systems(OnExit(state::AppState::Paused), pause::unfreeze_time)
+            .add_systems(
+                Update,
+                pause::handle_pause_key.run_if(in_state(state::AppState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (pause::handle_menu_input, pause::highlight_selection)
+                    .chain()
+                    .run_if(in_state(state::AppState::Paused)),
+            )
+            .init_resource::<controls_menu::ControlsSelection>()
+            .add_systems(
+                OnEnter(state::AppState::Controls),
+                controls_menu::open_controls_menu,
+            )
+            .add_systems(
+                Update,
+                (
+                    (
+                        controls_menu::handle_open_key,
+                        session::handle_session_keys,
+                        session::update_session_prompt,
+                    )
+                        .chain()
+                        .run_if(in_state(state::AppState::MainMenu)),
+                    (
+                        controls_menu::capture_binding,
+                        controls_menu::handle_menu_input,
+                        controls_menu::update_options,
+                    )
+                        .chain()
+                        .run_if(in_state(state::AppState::Controls)),
+                ),
+            );
+    }
+
+    /// Loads saved files once the app has had the chance to turn
+    /// `Persistence` off
+    fn finish(&self, app: &mut App) {
+        if app.world().resource::<Persistence>().enabled {
+            app.insert_resource(high_scores::HighScores::load())
+                .insert_resource(controls::Controls::load());
+        }
     }
 }
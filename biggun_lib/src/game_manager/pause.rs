@@ -0,0 +1,150 @@
+//! Pause menu shown over a run. Pausing freezes virtual time, which stops
+//! everything timed by it: the countdown, fish struggling and spawning.
+
+use super::{
+    config::Config,
+    controls::{Action, ActionInput},
+    replay::RunInput,
+    state::AppState,
+};
+use bevy::prelude::*;
+
+/// An entry in the pause menu
+#[derive(Component, Clone, Copy, PartialEq)]
+pub enum PauseOption {
+    Resume,
+    RestartStage,
+    MainMenu,
+}
+
+impl PauseOption {
+    /// All options, in the order they are shown
+    const ALL: [PauseOption; 3] = [Self::Resume, Self::RestartStage, Self::MainMenu];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Resume => "RESUME",
+            Self::RestartStage => "RESTART STAGE",
+            Self::MainMenu => "MAIN MENU",
+        }
+    }
+}
+
+/// Index into `PauseOption::ALL` of the highlighted option
+#[derive(Resource, Default)]
+pub struct PauseSelection(usize);
+
+pub fn freeze_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+pub fn unfreeze_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+/// Spawns the pause overlay
+pub fn open_pause_menu(
+    mut commands: Commands,
+    mut selection: ResMut<PauseSelection>,
+    config: Res<Config>,
+    asset_server: Res<AssetServer>,
+) {
+    selection.0 = 0;
+
+    let font = asset_server.load("kodemono.ttf");
+    let visuals = &config.visuals;
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: percent(100),
+                height: percent(100),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: px(visuals.score_padding),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0., 0., 0., visuals.blinds_opacity)),
+            // Above the HUD
+            GlobalZIndex(1),
+            DespawnOnExit(AppState::Paused),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("PAUSED"),
+                TextFont::from(font.clone()).with_font_size(visuals.score_font_size),
+                TextColor(Color::WHITE),
+                TextLayout::new_with_justify(Justify::Center),
+            ));
+            for option in PauseOption::ALL {
+                parent.spawn((
+                    Text::new(option.label()),
+                    TextFont::from(font.clone()).with_font_size(visuals.info_font_size),
+                    TextColor(Color::WHITE),
+                    TextLayout::new_with_justify(Justify::Center),
+                    option,
+                ));
+            }
+        });
+}
+
+/// Pauses the run when the pause action is pressed
+pub fn handle_pause_key(actions: ActionInput, mut next_state: ResMut<NextState<AppState>>) {
+    if actions.just_pressed(Action::Pause) {
+        next_state.set(AppState::Paused);
+    }
+}
+
+/// Moves the selection up and down and picks an option with the confirm
+/// action. The pause action resumes.
+pub fn handle_menu_input(
+    actions: ActionInput,
+    mut selection: ResMut<PauseSelection>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut run_input: ResMut<RunInput>,
+) {
+    let count = PauseOption::ALL.len();
+    if actions.just_pressed(Action::MenuUp) {
+        selection.0 = (selection.0 + count - 1) % count;
+    }
+    if actions.just_pressed(Action::MenuDown) {
+        selection.0 = (selection.0 + 1) % count;
+    }
+
+    if actions.just_pressed(Action::Pause) {
+        next_state.set(AppState::Playing);
+        return;
+    }
+    if !actions.just_pressed(Action::Confirm) {
+        return;
+    }
+    match PauseOption::ALL[selection.0] {
+        PauseOption::Resume => next_state.set(AppState::Playing),
+        PauseOption::RestartStage => {
+            // Restarted on the next tick, so that replays see it
+            run_input.restart = true;
+            next_state.set(AppState::Playing);
+        }
+        PauseOption::MainMenu => next_state.set(AppState::MainMenu),
+    }
+}
+
+/// Marks the selected option
+pub fn highlight_selection(
+    selection: Res<PauseSelection>,
+    options: Query<(&PauseOption, &mut Text)>,
+) {
+    if !selection.is_changed() {
+        return;
+    }
+    let selected = PauseOption::ALL[selection.0];
+    for (option, mut text) in options {
+        text.0 = if *option == selected {
+            format!("> {} <", option.label())
+        } else {
+            option.label().to_string()
+        };
+    }
+}
@@ -0,0 +1,353 @@
+//! Recording and playback of runs. A run is reproduced exactly from its seed
+//! and the input given on each `FixedUpdate` tick, so a replay file only
+//! stores those. Replays assume the same config and species files as the
+//! recorded run.
+
+use super::{
+    controls::{Action, ActionInput},
+    rng::GameRng,
+    session::Session,
+    state::{GameState, NextStageEvent},
+};
+use crate::player::Player;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// Bumped whenever recorded runs would play back differently
+const REPLAY_VERSION: u32 = 16;
+
+/// A single player's input for the current tick
+#[derive(Default, Clone, Copy, PartialEq)]
+pub struct PlayerInput {
+    /// How hard the hook is steered, from -1 (left) to 1 (right)
+    pub steer: f32,
+    pub reel: bool,
+}
+
+/// Input for the current `FixedUpdate` tick. Read from the players' controls,
+/// or from a replay when one is playing back.
+#[derive(Resource, Default, Clone, Copy, PartialEq)]
+pub struct RunInput {
+    /// Indexed by `Player::index`
+    pub players: [PlayerInput; Player::MAX],
+    /// The stage is restarted before this tick. Set by the pause menu.
+    pub restart: bool,
+}
+
+/// `RunInput` as stored in replays: the buttons held, one bit each, and each
+/// player's steering
+type EncodedInput = (u8, [i8; Player::MAX]);
+
+impl RunInput {
+    /// Steering is stored in replays with this many steps each way
+    const STEER_STEPS: f32 = 127.;
+
+    /// Packs the input as stored in replays. Bit 0 is `restart`, bit `n + 1`
+    /// is player `n` reeling.
+    fn encode(self) -> EncodedInput {
+        let mut buttons = u8::from(self.restart);
+        let mut steers = [0; Player::MAX];
+        for (index, player) in self.players.iter().enumerate() {
+            buttons |= u8::from(player.reel) << (index + 1);
+            steers[index] = (player.steer * Self::STEER_STEPS).round() as i8;
+        }
+        (buttons, steers)
+    }
+
+    fn decode((buttons, steers): EncodedInput) -> RunInput {
+        let mut input = RunInput {
+            restart: buttons & 1 != 0,
+            ..default()
+        };
+        for (index, player) in input.players.iter_mut().enumerate() {
+            player.steer = steers[index] as f32 / Self::STEER_STEPS;
+            player.reel = buttons & 1 << (index + 1) != 0;
+        }
+        input
+    }
+}
+
+/// A recorded run
+#[derive(Serialize, Deserialize, Default)]
+pub struct Replay {
+    version: u32,
+    seed: u64,
+    session: Session,
+    /// Input for every tick of the run, run-length encoded as encoded
+    /// `RunInput`s and how many ticks in a row they were held
+    inputs: Vec<(EncodedInput, u32)>,
+}
+
+impl Replay {
+    /// Reads a replay file from `path`
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Replay, ReplayError> {
+        let path = path.as_ref();
+        let error = |message: String| ReplayError {
+            path: path.to_path_buf(),
+            message,
+        };
+        let source = std::fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+        let replay: Replay = ron::from_str(&source).map_err(|e| error(e.to_string()))?;
+        if replay.version != REPLAY_VERSION {
+            return Err(error(format!(
+                "recorded with replay version {}, expected {REPLAY_VERSION}",
+                replay.version
+            )));
+        }
+        Ok(replay)
+    }
+
+    /// Seed of the recorded run
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Players and mode of the recorded run
+    pub fn session(&self) -> Session {
+        self.session
+    }
+}
+
+/// Describes why a replay file could not be used
+#[derive(Debug)]
+pub struct ReplayError {
+    path: PathBuf,
+    message: String,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "could not load replay {}: {}",
+            self.path.display(),
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// Records each run, saving it to `path` when the run ends. Only the latest
+/// run is kept.
+#[derive(Resource)]
+pub struct Recorder {
+    path: PathBuf,
+    replay: Replay,
+}
+
+impl Recorder {
+    pub fn new(path: impl Into<PathBuf>) -> Recorder {
+        Recorder {
+            path: path.into(),
+            replay: Replay::default(),
+        }
+    }
+
+    fn push(&mut self, input: RunInput) {
+        let input = input.encode();
+        match self.replay.inputs.last_mut() {
+            Some((last, ticks)) if *last == input => *ticks += 1,
+            _ => self.replay.inputs.push((input, 1)),
+        }
+    }
+}
+
+/// Plays back a replay in place of the keyboard. Every run started replays
+/// it from the beginning.
+#[derive(Resource)]
+pub struct Playback {
+    replay: Replay,
+    /// Index into `replay.inputs`
+    index: usize,
+    /// Ticks already played of `replay.inputs[index]`
+    ticks: u32,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Playback {
+        Playback {
+            replay,
+            index: 0,
+            ticks: 0,
+        }
+    }
+
+    /// Input for the next tick. No input once the replay has ended.
+    fn next(&mut self) -> RunInput {
+        let Some(&(input, ticks)) = self.replay.inputs.get(self.index) else {
+            return RunInput::default();
+        };
+        self.ticks += 1;
+        if self.ticks >= ticks {
+            self.index += 1;
+            self.ticks = 0;
+        }
+        RunInput::decode(input)
+    }
+}
+
+/// Starts recording or rewinds the playback at the start of a run. Must run
+/// after the run's seed is picked.
+pub fn start_run(
+    rng: Res<GameRng>,
+    session: Res<Session>,
+    recorder: Option<ResMut<Recorder>>,
+    playback: Option<ResMut<Playback>>,
+) {
+    if let Some(mut recorder) = recorder {
+        recorder.replay = Replay {
+            version: REPLAY_VERSION,
+            seed: rng.seed(),
+            session: *session,
+            inputs: Vec::new(),
+        };
+    }
+    if let Some(mut playback) = playback {
+        playback.index = 0;
+        playback.ticks = 0;
+    }
+}
+
+/// Saves the recording once a run is over
+pub fn save_recording(recorder: Res<Recorder>) {
+    let result = ron::to_string(&recorder.replay)
+        .map_err(|e| e.to_string())
+        .and_then(|text| std::fs::write(&recorder.path, text).map_err(|e| e.to_string()));
+    match result {
+        Ok(()) => info!("Saved replay to {}", recorder.path.display()),
+        Err(e) => warn!("Could not save replay to {}: {e}", recorder.path.display()),
+    }
+}
+
+/// Reads this tick's input, from the replay if one is playing back, and
+/// records it
+pub fn read_input(
+    actions: ActionInput,
+    session: Res<Session>,
+    mut input: ResMut<RunInput>,
+    playback: Option<ResMut<Playback>>,
+    recorder: Option<ResMut<Recorder>>,
+) {
+    *input = match playback {
+        Some(mut playback) => playback.next(),
+        None => {
+            let mut read = RunInput {
+                // Requested since the last tick
+                restart: input.restart,
+                ..default()
+            };
+            for (index, player) in read.players.iter_mut().take(session.players).enumerate() {
+                player.steer = actions.steer(index);
+                player.reel = actions.pressed(Action::Reel(index));
+            }
+            // Rounded the same way as in replays, so playback matches exactly
+            RunInput::decode(read.encode())
+        }
+    };
+    if let Some(mut recorder) = recorder {
+        recorder.push(*input);
+    }
+}
+
+/// Restarts the stage when asked to by this tick's input. Score earned this
+/// stage is lost.
+pub fn restart_stage(
+    mut input: ResMut<RunInput>,
+    mut state: ResMut<GameState>,
+    mut commands: Commands,
+) {
+    if std::mem::take(&mut input.restart) {
+        state.clear_stage_score();
+        commands.trigger(NextStageEvent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(steers: [f32; Player::MAX], reels: [bool; Player::MAX], restart: bool) -> RunInput {
+        let mut input = RunInput {
+            restart,
+            ..default()
+        };
+        for (player, (steer, reel)) in input.players.iter_mut().zip(steers.into_iter().zip(reels)) {
+            *player = PlayerInput { steer, reel };
+        }
+        input
+    }
+
+    fn play(replay: Replay, ticks: usize) -> Vec<RunInput> {
+        let mut playback = Playback::new(replay);
+        (0..ticks).map(|_| playback.next()).collect()
+    }
+
+    #[test]
+    fn encoding_round_trips() {
+        let original = input([1., -1., 0.5, -0.25], [true, false, false, true], true);
+        let decoded = RunInput::decode(original.encode());
+        assert!(decoded.restart);
+        for (decoded, original) in decoded.players.iter().zip(original.players) {
+            assert_eq!(decoded.reel, original.reel);
+            assert!((decoded.steer - original.steer).abs() <= 0.5 / RunInput::STEER_STEPS);
+        }
+        // Already rounded input is stored exactly
+        assert!(RunInput::decode(decoded.encode()) == decoded);
+        assert!(!RunInput::decode(RunInput::default().encode()).restart);
+    }
+
+    #[test]
+    fn records_held_input_as_one_run() {
+        let held = input([1., 0., 0., 0.], [true, false, false, false], false);
+        let mut recorder = Recorder::new("unused.ron");
+        for _ in 0..5 {
+            recorder.push(held);
+        }
+        recorder.push(RunInput::default());
+        assert_eq!(recorder.replay.inputs.len(), 2);
+        assert_eq!(recorder.replay.inputs[0].1, 5);
+        let played = play(recorder.replay, 7);
+        assert!(played[..5].iter().all(|&tick| tick == held));
+        assert!(played[5..].iter().all(|&tick| tick == RunInput::default()));
+    }
+
+    #[test]
+    fn records_alternating_input_tick_by_tick() {
+        let reeling = input([0.; Player::MAX], [false, true, false, false], false);
+        let ticks: Vec<_> = (0..6)
+            .map(|tick| {
+                if tick % 2 == 0 {
+                    reeling
+                } else {
+                    RunInput::default()
+                }
+            })
+            .collect();
+        let mut recorder = Recorder::new("unused.ron");
+        for &tick in &ticks {
+            recorder.push(tick);
+        }
+        assert_eq!(recorder.replay.inputs.len(), 6);
+        assert!(recorder.replay.inputs.iter().all(|&(_, ticks)| ticks == 1));
+        assert!(play(recorder.replay, 6) == ticks);
+    }
+
+    #[test]
+    fn rejects_other_replay_versions() {
+        let path = std::env::temp_dir().join(format!("biggun-replay-{}.ron", std::process::id()));
+        let replay = Replay {
+            version: REPLAY_VERSION - 1,
+            ..default()
+        };
+        std::fs::write(&path, ron::to_string(&replay).unwrap()).unwrap();
+        let result = Replay::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        let error = result.err().expect("an old replay should be rejected");
+        assert!(error.to_string().contains("replay version"));
+    }
+}
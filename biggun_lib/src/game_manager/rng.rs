@@ -0,0 +1,67 @@
+//! Seedable randomness. Every random roll in gameplay goes through
+//! [`GameRng`] so a run can be reproduced from its seed.
+
+use bevy::prelude::*;
+use rand::{SeedableRng, rngs::StdRng};
+
+/// Random number streams for a run, all derived from one seed. Each system
+/// rolling dice gets its own stream so that the order systems run in doesn't
+/// change the outcome.
+#[derive(Resource)]
+pub struct GameRng {
+    /// Seed used for every run, e.g. given on the command line. A new seed is
+    /// picked for each run when `None`.
+    fixed_seed: Option<u64>,
+    /// Seed of the current run
+    seed: u64,
+    /// Fish spawning, including the stats each fish is rolled with
+    pub spawn: StdRng,
+    /// Hooked fish changing direction
+    pub struggle: StdRng,
+    /// Fish deciding whether to go for a lure
+    pub lure: StdRng,
+    /// Predators deciding what to do with the catch they attack
+    pub predation: StdRng,
+}
+
+impl GameRng {
+    /// Offsets mixed into the seed for each stream
+    const SPAWN_STREAM: u64 = 1;
+    const STRUGGLE_STREAM: u64 = 2;
+    const LURE_STREAM: u64 = 3;
+    const PREDATION_STREAM: u64 = 4;
+
+    /// Uses `seed` for every run
+    pub fn from_seed(seed: u64) -> GameRng {
+        GameRng::with_seed(Some(seed), seed)
+    }
+
+    fn with_seed(fixed_seed: Option<u64>, seed: u64) -> GameRng {
+        GameRng {
+            fixed_seed,
+            seed,
+            spawn: StdRng::seed_from_u64(seed ^ Self::SPAWN_STREAM),
+            struggle: StdRng::seed_from_u64(seed ^ Self::STRUGGLE_STREAM),
+            lure: StdRng::seed_from_u64(seed ^ Self::LURE_STREAM),
+            predation: StdRng::seed_from_u64(seed ^ Self::PREDATION_STREAM),
+        }
+    }
+
+    /// Reseeds all streams at the start of a run
+    pub fn start_run(&mut self) {
+        let seed = self.fixed_seed.unwrap_or_else(rand::random);
+        *self = GameRng::with_seed(self.fixed_seed, seed);
+    }
+
+    /// Seed of the current run
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Default for GameRng {
+    /// Picks a new seed for each run
+    fn default() -> Self {
+        GameRng::with_seed(None, rand::random())
+    }
+}
@@ -1,15 +1,24 @@
-//! Scene setup and transitioning
+//! Scene setup for each `AppState`. Scene entities are scoped to the state
+//! that spawned them and despawn when it is exited.
 
 use crate::{
-    environment::fish::SpawnHandler,
-    game_manager::state::GameOverEvent,
+    environment::{
+        fish::SpawnHandler,
+        obstacle::{self, Obstacle},
+    },
     physics::Velocity,
     player::{
-        OwnedByPlayer, PlayerOwns,
-        fisherman::{Fisherman, FishingLine, Rod},
-        hook::Hook,
+        OwnedByPlayer, Player,
+        fisherman::{Fisherman, Rod},
+        hook::{Cast, Hook},
+        line::FishingLine,
+    },
+    utils::{
+        layers::Layer,
+        ui::{
+            BossBar, BossMeter, BossName, LureDisplay, ScoreDisplay, TargetDisplay, TensionMeter,
+        },
     },
-    utils::{layers::Layer, ui::ScoreDisplay},
 };
 
 use bevy::{
@@ -18,35 +27,23 @@ use bevy::{
 
 use super::{
     config::Config,
-    state::{self, CountdownTimer, GameState, NextStageEvent, StartGameEvent},
+    controls::Controls,
+    high_scores::{HighScoreDisplay, HighScores, NameEntry},
+    rng::GameRng,
+    session::{Session, SessionPrompt},
+    state::{self, AppState, CountdownTimer, GameState, InGame, NextStageEvent, StageClearEvent},
 };
 use bevy_prototype_lyon::prelude::*;
 
-#[derive(Event)]
-pub struct SceneTransitionEvent;
-
-/// Attached entities will despawn on scene transition.
+/// Shown between stages. The next stage starts when `timer` finishes.
 #[derive(Component)]
-pub struct SceneVolatile;
-
-impl Default for SceneVolatile {
-    fn default() -> SceneVolatile {
-        SceneVolatile
-    }
-}
-
-pub fn on_scene_transition(
-    _: On<SceneTransitionEvent>,
-    volatiles: Query<Entity, With<SceneVolatile>>,
-    mut commands: Commands,
-) {
-    for v in volatiles {
-        commands.entity(v).despawn();
-    }
+#[require(DespawnOnExit<InGame> = DespawnOnExit(InGame))]
+pub struct StageClearScreen {
+    pub timer: Timer,
 }
 
-/// Loads into the main menu
-pub fn load_main_menu(mut commands: Commands, asset_server: Res<AssetServer>, config: Res<Config>) {
+/// Spawns the parts of the world that stay for the whole game
+pub fn setup_world(mut commands: Commands, asset_server: Res<AssetServer>, config: Res<Config>) {
     // Camera
     commands.spawn((
         Camera2d,
@@ -115,22 +112,52 @@ pub fn load_main_menu(mut commands: Commands, asset_server: Res<AssetServer>, co
         },
     ));
 
-    // Fisherman
-    commands.spawn((
+    commands.spawn(fisherman(&asset_server, &config, 0., Player::default()));
+
+    commands.spawn(SpawnHandler {
+        timer: Timer::from_seconds(1.0, TimerMode::Once),
+    });
+}
+
+/// A fisherman standing in the boat at `x`
+fn fisherman(asset_server: &AssetServer, config: &Config, x: f32, player: Player) -> impl Bundle {
+    (
         Sprite {
             image: asset_server.load("fisherman.png"),
             ..default()
         },
         Transform {
-            translation: Vec3::new(0., config.water_level + 24., Layer::BG_DECORATIONS),
+            translation: Vec3::new(x, config.water_level + 24., Layer::BG_DECORATIONS),
             ..default()
         },
         Fisherman,
-    ));
+        player,
+    )
+}
 
-    commands.spawn(SpawnHandler {
-        timer: Timer::from_seconds(1.0, TimerMode::Once),
-    });
+/// Loads into the main menu
+#[allow(clippy::too_many_arguments)]
+pub fn load_main_menu(
+    mut commands: Commands,
+    mut state: ResMut<GameState>,
+    asset_server: Res<AssetServer>,
+    config: Res<Config>,
+    high_scores: Res<HighScores>,
+    controls: Res<Controls>,
+    session: Res<Session>,
+    floor: Query<&mut Transform, With<state::Floor>>,
+    fishermen: Query<&mut Transform, (With<Fisherman>, Without<state::Floor>)>,
+    obstacles: Query<Entity, With<Obstacle>>,
+) {
+    state.reset();
+    for mut floor_transform in floor {
+        floor_transform.translation.y = config.water_level - config.sample_stage.water_depth;
+    }
+    obstacle::spawn_obstacles(&mut commands, &obstacles, &config, &config.sample_stage);
+    // Only the first player stays once a run is over
+    for mut transform in fishermen {
+        transform.translation.x = Player::boat_x(0, 1);
+    }
 
     // UI
     let font = asset_server.load("kodemono.ttf");
@@ -147,7 +174,7 @@ pub fn load_main_menu(mut commands: Commands, asset_server: Res<AssetServer>, co
             ..default()
         },
         image_node,
-        SceneVolatile,
+        DespawnOnExit(AppState::MainMenu),
     ));
 
     commands.spawn((
@@ -157,26 +184,70 @@ pub fn load_main_menu(mut commands: Commands, asset_server: Res<AssetServer>, co
             top: percent(50),
             ..default()
         },
-        Text::new("press [ENTER] to start"),
+        Text::new(session.menu_prompt(&controls)),
+        SessionPrompt,
         TextFont::from(font.clone()).with_font_size(visuals.info_font_size),
         TextColor(Color::WHITE),
         TextLayout::new_with_justify(Justify::Center),
-        SceneVolatile,
+        DespawnOnExit(AppState::MainMenu),
+    ));
+
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            justify_self: JustifySelf::Center,
+            top: percent(60),
+            ..default()
+        },
+        HighScoreDisplay,
+        Text::new(high_scores.table_text()),
+        TextFont::from(font.clone()).with_font_size(visuals.info_font_size),
+        TextColor(Color::WHITE),
+        TextLayout::new_with_justify(Justify::Left),
+        DespawnOnExit(AppState::MainMenu),
     ));
 }
 
-/// Loads into the core gameplay loop **from main menu**.
+/// Loads into the core gameplay loop at the start of a run
 pub fn load_game(
-    _event: On<StartGameEvent>,
     mut commands: Commands,
     mut state: ResMut<GameState>,
     config: Res<Config>,
+    mut rng: ResMut<GameRng>,
+    session: Res<Session>,
     asset_server: Res<AssetServer>,
-    players: Query<Entity, With<PlayerOwns>>,
+    mut fishermen: Query<(Entity, &Player, &mut Transform)>,
 ) {
-    state.started = true;
-
-    for p in players {
+    state.start_run(&session);
+    rng.start_run();
+
+    // Line up the fishermen already in the boat, and bring in the rest
+    let count = session.players;
+    let mut players = vec![None; count];
+    for (entity, player, mut transform) in &mut fishermen {
+        if player.index < count {
+            transform.translation.x = Player::boat_x(player.index, count);
+            players[player.index] = Some(entity);
+        }
+    }
+    let players: Vec<Entity> = players
+        .into_iter()
+        .enumerate()
+        .map(|(index, entity)| {
+            entity.unwrap_or_else(|| {
+                let x = Player::boat_x(index, count);
+                commands
+                    .spawn((
+                        fisherman(&asset_server, &config, x, Player { index }),
+                        DespawnOnExit(InGame),
+                    ))
+                    .id()
+            })
+        })
+        .collect();
+
+    for (index, &p) in players.iter().enumerate() {
+        let x = Player::boat_x(index, count);
         commands.spawn((
             Sprite {
                 image: asset_server.load("rod.png"),
@@ -184,7 +255,7 @@ pub fn load_game(
             },
             Anchor::BOTTOM_RIGHT,
             Transform {
-                translation: Vec3::new(0., config.water_level + 10., Layer::FISHING_ROD),
+                translation: Vec3::new(x, config.water_level + 10., Layer::FISHING_ROD),
                 ..default()
             },
             Rod,
@@ -200,7 +271,7 @@ pub fn load_game(
                 translation: Vec3::new(0., 0., Layer::HOOK),
                 ..default()
             },
-            FishingLine,
+            FishingLine::default(),
             OwnedByPlayer(p),
         ));
 
@@ -211,7 +282,7 @@ pub fn load_game(
                 ..default()
             },
             Transform {
-                translation: Hook::start_pos(&config),
+                translation: Hook::start_pos(&config, x),
                 ..default()
             },
             Hook {
@@ -220,6 +291,11 @@ pub fn load_game(
                 density: 10.0,
                 hooked: false,
                 catch_radius: 8.,
+                tension: 0.,
+                line_strength: 100.,
+                slack_recovery: 25.,
+                cast_range: 150.,
+                cast: Cast::Ready,
             },
             Velocity(Vec2::ZERO),
             OwnedByPlayer(p),
@@ -243,7 +319,7 @@ pub fn load_game(
         TextColor(Color::WHITE),
         TextLayout::new_with_justify(Justify::Center),
         ScoreDisplay,
-        SceneVolatile,
+        DespawnOnExit(InGame),
     ));
 
     // Target score
@@ -258,9 +334,116 @@ pub fn load_game(
         TextFont::from(font.clone()).with_font_size(visuals.score_font_size),
         TextColor(Color::WHITE),
         TextLayout::new_with_justify(Justify::Center),
-        SceneVolatile,
+        TargetDisplay,
+        DespawnOnExit(InGame),
     ));
 
+    // Line tension, one meter per player
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: px(visuals.score_padding),
+                left: px(visuals.score_padding),
+                flex_direction: FlexDirection::Column,
+                row_gap: px(visuals.score_padding),
+                ..default()
+            },
+            DespawnOnExit(InGame),
+        ))
+        .with_children(|parent| {
+            for (index, &player) in players.iter().enumerate() {
+                let label = if players.len() > 1 {
+                    format!("P{} LINE", index + 1)
+                } else {
+                    "LINE".to_string()
+                };
+                parent
+                    .spawn(Node {
+                        align_items: AlignItems::Center,
+                        column_gap: px(visuals.score_padding),
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn((
+                            Text::new(label),
+                            TextFont::from(font.clone()).with_font_size(visuals.info_font_size),
+                            TextColor(Color::WHITE),
+                        ));
+                        row.spawn((
+                            Node {
+                                width: px(visuals.tension_meter_width),
+                                height: px(visuals.info_font_size / 2.),
+                                ..default()
+                            },
+                            BackgroundColor(Color::srgba(1., 1., 1., 0.2)),
+                        ))
+                        .with_children(|meter| {
+                            meter.spawn((
+                                Node {
+                                    width: percent(0),
+                                    height: percent(100),
+                                    ..default()
+                                },
+                                BackgroundColor(Color::WHITE),
+                                TensionMeter,
+                                OwnedByPlayer(player),
+                            ));
+                        });
+                        row.spawn((
+                            TextFont::from(font.clone()).with_font_size(visuals.info_font_size),
+                            TextColor(Color::WHITE),
+                            LureDisplay,
+                            OwnedByPlayer(player),
+                        ));
+                    });
+            }
+        });
+
+    // Boss name and stamina
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: px(visuals.score_padding),
+                right: px(visuals.score_padding),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::End,
+                row_gap: px(visuals.score_padding),
+                ..default()
+            },
+            Visibility::Hidden,
+            BossBar,
+            DespawnOnExit(InGame),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextFont::from(font.clone()).with_font_size(visuals.info_font_size),
+                TextColor(Color::WHITE),
+                BossName,
+            ));
+            parent
+                .spawn((
+                    Node {
+                        width: px(visuals.tension_meter_width),
+                        height: px(visuals.info_font_size / 2.),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgba(1., 1., 1., 0.2)),
+                ))
+                .with_children(|meter| {
+                    meter.spawn((
+                        Node {
+                            width: percent(100),
+                            height: percent(100),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(1., 0.8, 0.2)),
+                        BossMeter,
+                    ));
+                });
+        });
+
     // Countdown
     commands.spawn((
         Node {
@@ -277,7 +460,7 @@ pub fn load_game(
         CountdownTimer {
             timer: Timer::new(config.sample_stage.time, TimerMode::Once),
         },
-        SceneVolatile,
+        DespawnOnExit(InGame),
     ));
 
     // Left blind
@@ -289,7 +472,7 @@ pub fn load_game(
             scale: Vec3::new(10000., 10000., 1.),
             ..default()
         },
-        SceneVolatile,
+        DespawnOnExit(InGame),
     ));
 
     // Right blind
@@ -301,30 +484,40 @@ pub fn load_game(
             scale: Vec3::new(10000., 10000., 1.),
             ..default()
         },
-        SceneVolatile,
+        DespawnOnExit(InGame),
     ));
 
     // Go to next stage
     commands.trigger(NextStageEvent);
 }
 
+/// Shows the final score over the frozen lake, celebrating a won run
 pub fn game_over_screen(
-    _: On<GameOverEvent>,
     mut commands: Commands,
-    mut state: ResMut<GameState>,
-    velocity_query: Query<&mut Velocity>,
+    state: Res<GameState>,
     config: Res<Config>,
+    high_scores: Res<HighScores>,
+    controls: Res<Controls>,
+    rng: Res<GameRng>,
     asset_server: Res<AssetServer>,
 ) {
-    state.started = false;
-
     let font = asset_server.load("kodemono.ttf");
     let visuals = &config.visuals;
 
-    // "freeze" everything
-    for mut v in velocity_query {
-        v.0 = Vec2::ZERO;
-    }
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            justify_self: JustifySelf::Center,
+            top: percent(5),
+            ..default()
+        },
+        HighScoreDisplay,
+        Text::new(high_scores.table_text()),
+        TextFont::from(font.clone()).with_font_size(visuals.info_font_size),
+        TextColor(Color::WHITE),
+        TextLayout::new_with_justify(Justify::Left),
+        DespawnOnExit(AppState::GameOver),
+    ));
 
     commands.spawn((
         Node {
@@ -333,24 +526,86 @@ pub fn game_over_screen(
             top: percent(50),
             ..default()
         },
-        Text::new(format!("GAME OVER\n\nFINAL SCORE: {}", state.score)),
+        Text::new({
+            let title = if state.won {
+                "ALL STAGES CLEAR"
+            } else {
+                "GAME OVER"
+            };
+            if state.competitive {
+                format!("{title}\n\n{}", state.standings_text(false))
+            } else {
+                format!("{title}\n\nFINAL SCORE: {}", state.final_score())
+            }
+        }),
         TextFont::from(font.clone()).with_font_size(visuals.score_font_size),
         TextColor(Color::WHITE),
         TextLayout::new_with_justify(Justify::Center),
-        SceneVolatile,
+        DespawnOnExit(AppState::GameOver),
+        children![(
+            TextSpan::new(format!("\nSEED {}", rng.seed())),
+            TextFont::from(font.clone()).with_font_size(visuals.info_font_size),
+            TextColor(Color::WHITE),
+        )],
     ));
 
-    commands.spawn((
+    let mut prompt = commands.spawn((
         Node {
             position_type: PositionType::Absolute,
             justify_self: JustifySelf::Center,
             top: percent(66),
             ..default()
         },
-        Text::new("press [ENTER] to restart"),
+        Text::new(controls.restart_prompt()),
         TextFont::from(font.clone()).with_font_size(visuals.info_font_size),
         TextColor(Color::WHITE),
         TextLayout::new_with_justify(Justify::Center),
-        SceneVolatile,
+        DespawnOnExit(AppState::GameOver),
+    ));
+
+    // A new record asks for a name before the restart prompt is shown
+    if high_scores.qualifies(state.final_score(), config.high_score_count) {
+        let entry = NameEntry::default();
+        prompt.insert((Text::new(entry.prompt()), entry));
+    }
+}
+
+/// Announces a cleared stage until the next one starts
+pub fn stage_clear_screen(
+    _: On<StageClearEvent>,
+    mut commands: Commands,
+    state: Res<GameState>,
+    config: Res<Config>,
+    asset_server: Res<AssetServer>,
+) {
+    let font = asset_server.load("kodemono.ttf");
+    let visuals = &config.visuals;
+
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            justify_self: JustifySelf::Center,
+            top: percent(50),
+            ..default()
+        },
+        Text::new(if state.competitive {
+            format!(
+                "STAGE {} CLEAR\n\n{}",
+                state.stage_number(),
+                state.standings_text(true)
+            )
+        } else {
+            format!(
+                "STAGE {} CLEAR\n\nSCORE: {}",
+                state.stage_number(),
+                state.score
+            )
+        }),
+        TextFont::from(font.clone()).with_font_size(visuals.score_font_size),
+        TextColor(Color::WHITE),
+        TextLayout::new_with_justify(Justify::Center),
+        StageClearScreen {
+            timer: Timer::new(config.stage_clear_time, TimerMode::Once),
+        },
     ));
 }
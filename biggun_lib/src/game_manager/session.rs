@@ -0,0 +1,107 @@
+//! Who is playing the next run: how many players share the lake and whether
+//! they compete or work together. Picked on the main menu.
+
+use super::controls::{Action, ActionInput, Controls};
+use crate::player::Player;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How the scores of several players count towards clearing a stage
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+pub enum PlayMode {
+    /// Every player's catches go to one team score. The stage target is
+    /// multiplied by the number of players.
+    #[default]
+    Coop,
+    /// Each player scores for themselves. The first to reach the stage target
+    /// clears the stage, and the best total wins the run.
+    Competitive,
+}
+
+impl PlayMode {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Coop => "CO-OP",
+            Self::Competitive => "COMPETITIVE",
+        }
+    }
+}
+
+/// Players and mode of the next run
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct Session {
+    /// From 1 to `Player::MAX`
+    pub players: usize,
+    pub mode: PlayMode,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session {
+            players: 1,
+            mode: PlayMode::default(),
+        }
+    }
+}
+
+impl Session {
+    /// True when players score separately
+    pub fn competitive(&self) -> bool {
+        self.players > 1 && self.mode == PlayMode::Competitive
+    }
+
+    /// Main menu prompt listing how to start and change the session
+    pub fn menu_prompt(&self, controls: &Controls) -> String {
+        let mut prompt = format!(
+            "press [{}] to start\npress [{}] for controls\n\n[{}/{}] PLAYERS: {}",
+            controls.confirm.key_label(),
+            controls.open_controls.key_label(),
+            controls.fewer_players.key_label(),
+            controls.more_players.key_label(),
+            self.players
+        );
+        if self.players > 1 {
+            prompt += &format!(
+                "   [{}] MODE: {}",
+                controls.switch_mode.key_label(),
+                self.mode.label()
+            );
+        }
+        prompt
+    }
+}
+
+/// Main menu text showing the session
+#[derive(Component)]
+#[require(Text)]
+pub struct SessionPrompt;
+
+/// Adds or takes away players and switches the mode with their actions
+pub fn handle_session_keys(actions: ActionInput, mut session: ResMut<Session>) {
+    if actions.just_pressed(Action::FewerPlayers) {
+        session.players = (session.players - 1).max(1);
+    }
+    if actions.just_pressed(Action::MorePlayers) {
+        session.players = (session.players + 1).min(Player::MAX);
+    }
+    if actions.just_pressed(Action::SwitchMode) {
+        session.mode = match session.mode {
+            PlayMode::Coop => PlayMode::Competitive,
+            PlayMode::Competitive => PlayMode::Coop,
+        };
+    }
+}
+
+/// Keeps the main menu prompt in sync with the session
+pub fn update_session_prompt(
+    session: Res<Session>,
+    controls: Res<Controls>,
+    prompts: Query<&mut Text, With<SessionPrompt>>,
+) {
+    if !session.is_changed() && !controls.is_changed() {
+        return;
+    }
+    for mut text in prompts {
+        text.0 = session.menu_prompt(&controls);
+    }
+}
@@ -1,14 +1,53 @@
 //! Contain and manipulate game state and score
 use std::time::Duration;
 
-use crate::{environment::fish::Fish, game_manager::scenes::SceneTransitionEvent};
+use crate::{
+    environment::{
+        boss::BossEncounter,
+        fish::{Fish, SpawnHandler},
+        obstacle::{self, Obstacle},
+    },
+    game_manager::scenes::StageClearScreen,
+    player::{OwnedByPlayer, Player, hook::Hook},
+    utils::ui::{ScoreDisplay, TargetDisplay},
+    utils::units::Ounces,
+};
 
-use super::config::{Config, StageConfig};
+use super::{
+    config::{Config, StageConfig},
+    controls::{Action, ActionInput},
+    session::Session,
+};
 
 use bevy::prelude::*;
 
-#[derive(Event)]
-pub struct StartGameEvent;
+/// Top level flow of the game
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AppState {
+    #[default]
+    MainMenu,
+    Playing,
+    Paused,
+    GameOver,
+    /// Rebinding controls, opened from the main menu
+    Controls,
+}
+
+/// Exists while a run is in progress, paused or not. Entities belonging to a
+/// run are scoped to this state so that they are despawned once it ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InGame;
+
+impl ComputedStates for InGame {
+    type SourceStates = AppState;
+
+    fn compute(sources: AppState) -> Option<Self> {
+        match sources {
+            AppState::Playing | AppState::Paused => Some(InGame),
+            AppState::MainMenu | AppState::GameOver | AppState::Controls => None,
+        }
+    }
+}
 
 #[derive(Event)]
 pub struct NextStageEvent;
@@ -16,50 +55,194 @@ pub struct NextStageEvent;
 #[derive(Event)]
 pub struct GameOverEvent;
 
+/// Triggered once when the current stage's target score is reached
+#[derive(Event)]
+pub struct StageClearEvent;
+
 /// Resource data pertaining to the state of the game
-#[derive(Resource)]
+#[derive(Resource, Default)]
 pub struct GameState {
     /// The current number of fish in the game
     pub fish_count: u32,
-    /// The stage we're on, used as an index for `stages` in config
-    stage: usize,
-    /// False when on the main menu
-    pub started: bool,
+    /// The stage we're on, used as an index for `stages` in config. `None`
+    /// until a run is started from the main menu.
+    stage: Option<usize>,
+    /// Score of the current stage, across all players
+    pub score: u32,
+    /// Score banked from previously cleared stages, across all players
+    pub total_score: u32,
+    /// Each player's share of `score` and `total_score`, indexed by
+    /// `Player::index`
+    pub players: Vec<PlayerScore>,
+    /// Whether players score separately
+    pub competitive: bool,
+    /// Weight of the heaviest fish landed this run
+    pub biggest_fish: Ounces,
+    /// Every stage was cleared, ending the run in victory rather than on a
+    /// countdown running out
+    pub won: bool,
+}
+
+/// Score of a single player
+#[derive(Default, Clone)]
+pub struct PlayerScore {
     /// Score of the current stage
     pub score: u32,
+    /// Score banked from previously cleared stages
+    pub total_score: u32,
+}
+
+impl PlayerScore {
+    pub fn final_score(&self) -> u32 {
+        self.total_score + self.score
+    }
 }
 
 impl GameState {
-    /// Gets current stage or uses `config.sample_stage` as a fallback. Final
-    /// stage in config gets repeated forever, but `stage` can still count up.
+    /// Gets current stage or uses `config.sample_stage` as a fallback. A
+    /// `stage` past the end of the config gets its final stage.
     pub fn cur_stage<'a>(&self, config: &'a Config) -> &'a StageConfig {
-        if !self.started || config.stages.is_empty() {
-            &config.sample_stage
-        } else {
-            &config.stages[self.stage.clamp(0, config.stages.len())]
+        match self.stage {
+            Some(stage) if !config.stages.is_empty() => {
+                &config.stages[stage.min(config.stages.len() - 1)]
+            }
+            _ => &config.sample_stage,
         }
     }
 
-    /// Increments stage by 1
+    /// Resets state for a new run of `session` starting on the first stage
+    pub fn start_run(&mut self, session: &Session) {
+        *self = GameState {
+            stage: Some(0),
+            players: vec![PlayerScore::default(); session.players],
+            competitive: session.competitive(),
+            ..default()
+        };
+    }
+
+    /// Increments stage by 1 and banks the stage's score
     pub fn next_stage(&mut self) {
-        self.stage += 1;
+        if let Some(stage) = &mut self.stage {
+            *stage += 1;
+        }
+        self.total_score += self.score;
+        self.score = 0;
+        for player in &mut self.players {
+            player.total_score += player.score;
+            player.score = 0;
+        }
     }
 
-    /// Resets state to intial (`default()`) values
-    pub fn reset(&mut self) {
-        *self = GameState::default();
+    /// Adds `points` to the stage score, credited to player `player`
+    pub fn credit(&mut self, player: usize, points: u32) {
+        self.score += points;
+        if let Some(player) = self.players.get_mut(player) {
+            player.score += points;
+        }
+    }
+
+    /// Throws away the current stage's score
+    pub fn clear_stage_score(&mut self) {
+        self.score = 0;
+        for player in &mut self.players {
+            player.score = 0;
+        }
+    }
+
+    /// Score needed to clear the current stage. A co-op team must reach the
+    /// stage's target once per player; competing players each race to it.
+    pub fn target(&self, config: &Config) -> u32 {
+        let target = self.cur_stage(config).target_score;
+        if self.competitive {
+            target
+        } else {
+            target * self.players.len().max(1) as u32
+        }
+    }
+
+    /// True once the current stage's target has been reached
+    pub fn stage_cleared(&self, config: &Config) -> bool {
+        let target = self.target(config);
+        if self.competitive {
+            self.players.iter().any(|player| player.score >= target)
+        } else {
+            self.score >= target
+        }
+    }
+
+    /// Index of the player with the best `score`. Ties go to the lower index.
+    fn leader_by(&self, score: impl Fn(&PlayerScore) -> u32) -> usize {
+        self.players
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, player)| score(player))
+            .map_or(0, |(index, _)| index)
+    }
+
+    /// Jumps to `stage` without banking any score. Takes effect on the next
+    /// `NextStageEvent`.
+    pub fn skip_to(&mut self, stage: usize) {
+        self.stage = Some(stage);
+    }
+
+    /// True if clearing the current stage wins the run, there being no stage
+    /// after it
+    pub fn on_last_stage(&self, config: &Config) -> bool {
+        self.stage
+            .is_none_or(|stage| stage + 1 >= config.stages.len())
+    }
+
+    /// The stage we're on, counting from 1 for display
+    pub fn stage_number(&self) -> usize {
+        self.stage.map_or(0, |stage| stage + 1)
     }
-}
 
-impl Default for GameState {
-    fn default() -> Self {
-        GameState {
-            fish_count: 0,
-            stage: 0,
-            started: false,
-            score: 0,
+    /// Score across all stages, including the current one. When competing,
+    /// only the leader's score counts.
+    pub fn final_score(&self) -> u32 {
+        let leader = self.players.get(self.leader_by(PlayerScore::final_score));
+        match leader {
+            Some(leader) if self.competitive => leader.final_score(),
+            _ => self.total_score + self.score,
         }
     }
+
+    /// HUD text for the current stage's score
+    pub fn score_text(&self) -> String {
+        if !self.competitive {
+            return format!("SCORE {:08}", self.score);
+        }
+        let lines: Vec<_> = self
+            .players
+            .iter()
+            .enumerate()
+            .map(|(index, player)| format!("P{} {:08}", index + 1, player.score))
+            .collect();
+        lines.join("\n")
+    }
+
+    /// Announces the leader, then lists each player's score. `stage` picks
+    /// the current stage's scores over the whole run's.
+    pub fn standings_text(&self, stage: bool) -> String {
+        let score = |player: &PlayerScore| {
+            if stage {
+                player.score
+            } else {
+                player.final_score()
+            }
+        };
+        let mut text = format!("P{} WINS", self.leader_by(score) + 1);
+        for (index, player) in self.players.iter().enumerate() {
+            text += &format!("\nP{}: {}", index + 1, score(player));
+        }
+        text
+    }
+
+    /// Resets state to intial (`default()`) values
+    pub fn reset(&mut self) {
+        *self = GameState::default();
+    }
 }
 
 #[derive(Component)]
@@ -93,35 +276,97 @@ impl CountdownTimer {
 pub struct Floor;
 
 /// Transitions to `state`'s current stage
+#[allow(clippy::too_many_arguments)]
 pub fn stage_transition(
     _event: On<NextStageEvent>,
     config: Res<Config>,
-    state: Res<GameState>,
+    mut state: ResMut<GameState>,
     mut commands: Commands,
-    floor: Single<&mut Transform, With<Floor>>,
+    floor: Single<&mut Transform, (With<Floor>, Without<Hook>)>,
     fish: Query<Entity, With<Fish>>,
+    clear_screens: Query<Entity, With<StageClearScreen>>,
+    obstacles: Query<Entity, With<Obstacle>>,
+    hooks: Query<(Entity, &mut Hook, &mut Transform, &OwnedByPlayer)>,
+    players: Query<&Player>,
+    spawn_handler: Single<&mut SpawnHandler>,
     countdown_timer: Single<&mut CountdownTimer>,
+    mut score_display: Single<&mut Text, With<ScoreDisplay>>,
+    mut target_display: Single<&mut Text, (With<TargetDisplay>, Without<ScoreDisplay>)>,
 ) {
     let stage = state.cur_stage(&config);
 
-    fish.iter().for_each(|entity| {
+    fish.iter().chain(&clear_screens).for_each(|entity| {
         commands.entity(entity).despawn();
     });
+    state.fish_count = 0;
+    // Fish spawn the same way however long was spent in the menus
+    spawn_handler.into_inner().timer = Timer::from_seconds(1.0, TimerMode::Once);
+
+    // Any hooked fish was just despawned, so bring each hook back to its
+    // player to cast again
+    for (entity, mut hook, mut transform, owner) in hooks {
+        let index = players.get(owner.0).map_or(0, |player| player.index);
+        let x = Player::boat_x(index, state.players.len());
+        hook.reel_in(&mut commands, entity, &mut transform, &config, x);
+    }
+
     let mut floor_transform = floor.into_inner();
     floor_transform.translation.y = config.water_level - stage.water_depth;
+    obstacle::spawn_obstacles(&mut commands, &obstacles, &config, stage);
 
     countdown_timer.into_inner().reset_timer(stage.time);
+
+    score_display.0 = state.score_text();
+    target_display.0 = format!("{:08} TARGET", state.target(&config));
 }
 
-/// Handles input by triggering events
-pub fn handle_input(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+/// Clears the stage when its target score is reached or its boss landed, and
+/// ends the game when the countdown runs out first. Once cleared, the next
+/// stage starts after the stage clear screen has been shown for
+/// `config.stage_clear_time`, or the run is won if it was the last stage.
+pub fn evaluate_stage(
     mut commands: Commands,
+    config: Res<Config>,
     mut state: ResMut<GameState>,
+    encounter: Res<BossEncounter>,
+    countdown: Single<&mut CountdownTimer>,
+    clear_screen: Option<Single<(Entity, &mut StageClearScreen)>>,
+    time: Res<Time>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Enter) && !state.started {
-        commands.trigger(SceneTransitionEvent);
-        commands.trigger(StartGameEvent);
-        *state = GameState::default();
+    if let Some(clear_screen) = clear_screen {
+        let (entity, mut clear_screen) = clear_screen.into_inner();
+        clear_screen.timer.tick(time.delta());
+        if clear_screen.timer.is_finished() {
+            commands.entity(entity).despawn();
+            if state.on_last_stage(&config) {
+                state.won = true;
+                commands.trigger(GameOverEvent);
+            } else {
+                state.next_stage();
+                commands.trigger(NextStageEvent);
+            }
+        }
+        return;
+    }
+
+    let mut countdown = countdown.into_inner();
+    if state.stage_cleared(&config) || encounter.cleared(state.cur_stage(&config)) {
+        // Time left no longer matters
+        countdown.timer.pause();
+        commands.trigger(StageClearEvent);
+    } else if countdown.timer.is_finished() {
+        commands.trigger(GameOverEvent);
+    }
+}
+
+/// Ends the run, whether it was won or lost
+pub fn on_game_over(_: On<GameOverEvent>, mut next_state: ResMut<NextState<AppState>>) {
+    next_state.set(AppState::GameOver);
+}
+
+/// Starts a run from the main menu or game over screen
+pub fn handle_input(actions: ActionInput, mut next_state: ResMut<NextState<AppState>>) {
+    if actions.just_pressed(Action::Confirm) {
+        next_state.set(AppState::Playing);
     }
 }
@@ -1,8 +1,10 @@
 //! The library containing all gameplay systems for Biggun
 
+pub mod cli;
 pub mod environment;
 pub mod game_manager;
 pub mod physics;
 pub mod player;
 pub mod prelude;
+pub mod sim;
 pub(crate) mod utils;
@@ -0,0 +1,173 @@
+//! Uniform grid broadphase. Entities with a [`Collider`] are sorted into grid
+//! cells each tick, so collision checks only look at what is nearby instead of
+//! every entity in the lake.
+
+use bevy::{platform::collections::HashMap, prelude::*};
+
+/// Registers an entity with the [`SpatialGrid`]. Its bounds are a circle of
+/// `radius` around its `Transform`, which must cover every shape the entity
+/// collides with.
+///
+/// Colliders are placed by their own `Transform`, so children (e.g. a hooked
+/// fish) are left out of the grid.
+#[derive(Component, Clone, Copy)]
+pub struct Collider {
+    pub radius: f32,
+}
+
+/// Entities with a `Collider`, bucketed by the square cells their bounds
+/// overlap
+#[derive(Resource)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    /// Cells are kept once allocated, even when empty, so the grid doesn't
+    /// reallocate every tick
+    cells: HashMap<IVec2, Vec<Entity>>,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        SpatialGrid::new(32.)
+    }
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> SpatialGrid {
+        SpatialGrid {
+            cell_size,
+            cells: HashMap::default(),
+        }
+    }
+
+    /// Cells overlapped by a circle of `radius` around `center`
+    fn cells_around(&self, center: Vec2, radius: f32) -> impl Iterator<Item = IVec2> + use<> {
+        let min = ((center - radius) / self.cell_size).floor().as_ivec2();
+        let max = ((center + radius) / self.cell_size).floor().as_ivec2();
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+    }
+
+    fn clear(&mut self) {
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+    }
+
+    fn insert(&mut self, entity: Entity, center: Vec2, radius: f32) {
+        for cell in self.cells_around(center, radius) {
+            self.cells.entry(cell).or_default().push(entity);
+        }
+    }
+
+    /// Entities whose bounds may overlap a circle of `radius` around `center`,
+    /// sorted so that checks against them happen in a stable order
+    pub fn query(&self, center: Vec2, radius: f32) -> Vec<Entity> {
+        let mut found: Vec<Entity> = self
+            .cells_around(center, radius)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect();
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+}
+
+/// Rebuilds the grid from every top level collider's current position
+pub fn update_grid(
+    mut grid: ResMut<SpatialGrid>,
+    colliders: Query<(Entity, &Transform, &Collider), Without<ChildOf>>,
+) {
+    grid.clear();
+    for (entity, transform, collider) in colliders {
+        grid.insert(entity, transform.translation.truncate(), collider.radius);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(index: u32) -> Entity {
+        Entity::from_raw_u32(index).unwrap()
+    }
+
+    /// Checks that a collider at `center` is found by every query circle
+    /// overlapping it, around a ring of query centers at `distance`
+    fn assert_found(grid_size: f32, center: Vec2, radius: f32) {
+        let mut grid = SpatialGrid::new(grid_size);
+        grid.insert(entity(1), center, radius);
+        for query_radius in [0., 1., grid_size / 2., grid_size * 2.] {
+            // Just inside touching distance
+            let distance = (radius + query_radius) * 0.999;
+            for step in 0..32 {
+                let angle = step as f32 / 32. * std::f32::consts::TAU;
+                let at = center + Vec2::from_angle(angle) * distance;
+                assert_eq!(
+                    grid.query(at, query_radius),
+                    [entity(1)],
+                    "collider at {center} radius {radius}, query at {at} radius {query_radius}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn finds_overlapping_colliders() {
+        assert_found(32., Vec2::new(10., 10.), 5.);
+        assert_found(32., Vec2::new(100., -50.), 40.);
+    }
+
+    #[test]
+    fn finds_colliders_on_cell_boundaries() {
+        for center in [
+            Vec2::ZERO,
+            Vec2::new(32., 0.),
+            Vec2::new(32., 32.),
+            Vec2::new(31.999, 64.001),
+        ] {
+            assert_found(32., center, 0.);
+            assert_found(32., center, 3.);
+        }
+    }
+
+    #[test]
+    fn finds_colliders_at_negative_coordinates() {
+        for center in [
+            Vec2::new(-1., -1.),
+            Vec2::new(-32., -32.),
+            Vec2::new(-0.001, 15.),
+            Vec2::new(-100., -70.5),
+        ] {
+            assert_found(32., center, 0.);
+            assert_found(32., center, 12.);
+        }
+    }
+
+    #[test]
+    fn leaves_out_distant_colliders() {
+        let mut grid = SpatialGrid::new(32.);
+        grid.insert(entity(1), Vec2::new(-200., 0.), 5.);
+        grid.insert(entity(2), Vec2::new(5., 5.), 5.);
+        assert_eq!(grid.query(Vec2::ZERO, 10.), [entity(2)]);
+    }
+
+    #[test]
+    fn returns_each_entity_once_and_sorted() {
+        let mut grid = SpatialGrid::new(8.);
+        grid.insert(entity(3), Vec2::ZERO, 20.);
+        grid.insert(entity(1), Vec2::ZERO, 20.);
+        grid.insert(entity(2), Vec2::new(4., 4.), 1.);
+        let mut expected = vec![entity(1), entity(2), entity(3)];
+        expected.sort_unstable();
+        assert_eq!(grid.query(Vec2::ZERO, 20.), expected);
+    }
+
+    #[test]
+    fn clearing_forgets_colliders() {
+        let mut grid = SpatialGrid::new(32.);
+        grid.insert(entity(1), Vec2::ZERO, 5.);
+        grid.clear();
+        assert!(grid.query(Vec2::ZERO, 5.).is_empty());
+    }
+}
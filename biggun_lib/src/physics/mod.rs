@@ -1,23 +1,112 @@
 use bevy::prelude::*;
 
 use crate::{
-    environment::fish::Fish,
-    player::hook::{Hook, HookEvent},
+    environment::{
+        current,
+        fish::{Fish, FishBumpedEvent, HookedBy},
+        lake_is_live,
+        obstacle::{Obstacle, Sheltered},
+        species::Species,
+    },
+    game_manager::{
+        TickSet,
+        config::Config,
+        state::{AppState, GameState},
+    },
+    player::{
+        OwnedByPlayer,
+        hook::{Hook, HookEvent, HookLostEvent, HookedObjects},
+        lure::Lure,
+    },
 };
 
+pub(crate) mod grid;
+
 /// **VERY** simple physics plugin. Responsible for moving objects with
 /// velocity and making occasional collision checks.
 pub struct BiggunPhysicsPlugin;
 
 impl Plugin for BiggunPhysicsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(FixedUpdate, (apply_velocity, check_hook_fish_collision));
+        app.init_resource::<grid::SpatialGrid>().add_systems(
+            FixedUpdate,
+            (
+                (apply_velocity, apply_current).run_if(lake_is_live),
+                // Schools flock through the grid in the menus too
+                grid::update_grid.run_if(lake_is_live),
+                (
+                    check_hook_obstacle_collision,
+                    check_hook_fish_collision,
+                    update_shelter,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Playing)),
+            )
+                .chain()
+                .in_set(TickSet::Physics),
+        );
     }
 }
 
 #[derive(Component, Deref, DerefMut)]
 pub struct Velocity(pub Vec2);
 
+/// Carried along by the stage's currents on top of its own `Velocity`.
+/// Children (e.g. a hooked fish) move with their parent instead.
+#[derive(Component)]
+pub struct Drifts;
+
+/// Where a fish can be hooked or bumped, in world space
+pub struct Hitbox {
+    pub mouth: Vec2,
+    /// The body is every point within `body_radius` of the segment from
+    /// `body_from` to `body_to`
+    pub body_from: Vec2,
+    pub body_to: Vec2,
+    pub body_radius: f32,
+}
+
+impl Hitbox {
+    /// True if `point` is within `radius` of the mouth
+    pub fn bites(&self, point: Vec2, radius: f32) -> bool {
+        point.distance(self.mouth) < radius
+    }
+
+    /// How far `point` must move to get out of the body, if it is inside
+    pub fn body_overlap(&self, point: Vec2) -> Option<Vec2> {
+        let segment = self.body_to - self.body_from;
+        let along = if segment == Vec2::ZERO {
+            0.
+        } else {
+            ((point - self.body_from).dot(segment) / segment.length_squared()).clamp(0., 1.)
+        };
+        let offset = point - (self.body_from + segment * along);
+        let depth = self.body_radius - offset.length();
+        (depth > 0.).then(|| offset.normalize_or(Vec2::Y) * depth)
+    }
+
+    /// True if any part of the body is inside `rect`
+    pub fn body_overlaps(&self, rect: Rect) -> bool {
+        // Checked at a few points along the body, which is plenty for fish
+        // against obstacles many times their width
+        const SAMPLES: usize = 4;
+        (0..=SAMPLES).any(|sample| {
+            let point = self
+                .body_from
+                .lerp(self.body_to, sample as f32 / SAMPLES as f32);
+            point.distance(point.clamp(rect.min, rect.max)) < self.body_radius
+        })
+    }
+
+    /// How far the body reaches from `point`
+    pub fn reach(&self, point: Vec2) -> f32 {
+        point
+            .distance(self.body_from)
+            .max(point.distance(self.body_to))
+            + self.body_radius
+    }
+}
+
 impl Default for Velocity {
     fn default() -> Velocity {
         Velocity(Vec2::ZERO)
@@ -30,26 +119,163 @@ pub fn apply_velocity(mut query: Query<(&mut Transform, &Velocity)>, time: Res<T
     }
 }
 
+/// Moves drifting entities with the current where they are
+pub fn apply_current(
+    query: Query<&mut Transform, (With<Drifts>, Without<ChildOf>)>,
+    config: Res<Config>,
+    state: Res<GameState>,
+    time: Res<Time<Fixed>>,
+) {
+    let stage = state.cur_stage(&config);
+    if stage.currents.is_empty() {
+        return;
+    }
+    for mut transform in query {
+        let flow = current::flow_at(&config, stage, transform.translation.truncate());
+        transform.translation += flow.extend(0.0) * time.delta_secs();
+    }
+}
+
+/// Hooks the first free fish whose mouth is within each free hook's
+/// `catch_radius`, as long as it is striking at the hook's lure. Hooks can't pass
+/// through fish bodies; touching one pushes the hook back out and spooks the
+/// fish.
+#[allow(clippy::type_complexity)]
 pub fn check_hook_fish_collision(
-    hook_entity: Single<(Entity, &Transform, &Hook)>,
-    mut fish_query: Query<(Entity, &Transform), With<Fish>>,
+    hooks: Query<(Entity, &mut Transform, &Hook, &Lure)>,
+    fish_query: Query<(Entity, &Transform, &Fish), (Without<HookedBy>, Without<Hook>)>,
+    grid: Res<grid::SpatialGrid>,
+    species_assets: Res<Assets<Species>>,
+    config: Res<Config>,
     mut commands: Commands,
 ) {
-    let (hook_entity, hook_transform, hook) = hook_entity.into_inner();
-    if hook.hooked {
-        return;
+    // Fish hooked this tick, which can't be taken by another hook
+    let mut caught = Vec::new();
+    for (hook_entity, mut hook_transform, hook, lure) in hooks {
+        // A bare hook is bitten by any fish touching it
+        let bare = lure.config(&config).is_none();
+        if hook.hooked || !hook.in_water() {
+            continue;
+        }
+
+        let nearby = grid.query(hook_transform.translation.truncate(), hook.catch_radius);
+        for (fish_entity, fish_transform, fish) in fish_query.iter_many(nearby) {
+            let Some(species) = species_assets.get(fish.species()) else {
+                continue;
+            };
+            let hook_position = hook_transform.translation.truncate();
+            let hitbox = fish.hitbox(species, fish_transform.translation.truncate());
+            if !fish.spooked()
+                && (bare || fish.striking(hook_entity))
+                && !caught.contains(&fish_entity)
+                && hitbox.bites(hook_position, hook.catch_radius)
+            {
+                caught.push(fish_entity);
+                commands.trigger(HookEvent {
+                    hook_entity,
+                    fish_entity,
+                });
+                break;
+            }
+            if let Some(overlap) = hitbox.body_overlap(hook_position) {
+                hook_transform.translation += overlap.extend(0.);
+                if !fish.spooked() {
+                    commands.trigger(FishBumpedEvent { fish: fish_entity });
+                }
+            }
+        }
     }
+}
 
-    let hook_position = hook_transform.translation;
-    for (fish_entity, fish_transform) in &mut fish_query {
-        let fish_position = fish_transform.translation;
-        let dist = fish_position.distance(hook_position);
-        if dist < hook.catch_radius {
-            commands.trigger(HookEvent {
-                hook_entity,
-                fish_entity,
+/// Slows hooks moving through weeds and pushes them out of solid obstacles.
+/// A hooked fish dragged through an obstacle snags the line, building its
+/// tension until the line breaks and the catch is lost.
+#[allow(clippy::type_complexity)]
+pub fn check_hook_obstacle_collision(
+    mut commands: Commands,
+    hooks: Query<(
+        Entity,
+        &mut Transform,
+        &mut Hook,
+        &Velocity,
+        &OwnedByPlayer,
+        Option<&HookedObjects>,
+    )>,
+    fish_query: Query<&Fish, With<HookedBy>>,
+    obstacles: Query<(&Transform, &Obstacle), Without<Hook>>,
+    grid: Res<grid::SpatialGrid>,
+    species_assets: Res<Assets<Species>>,
+    time: Res<Time<Fixed>>,
+) {
+    for (entity, mut hook_transform, mut hook, velocity, owner, hooked_objects) in hooks {
+        let hook_position = hook_transform.translation.truncate();
+        // A hooked fish hangs from the hook's position
+        let hooked_fish = hooked_objects
+            .into_iter()
+            .flat_map(|objects| objects.iter())
+            .find_map(|fish| fish_query.get(fish).ok())
+            .and_then(|fish| {
+                let species = species_assets.get(fish.species())?;
+                Some(fish.hitbox(species, hook_position))
             });
-            return;
+        let reach = hooked_fish
+            .as_ref()
+            .map_or(0., |hitbox| hitbox.reach(hook_position));
+
+        for (obstacle_transform, obstacle) in obstacles.iter_many(grid.query(hook_position, reach))
+        {
+            let center = obstacle_transform.translation.truncate();
+            if let Some(overlap) = obstacle.overlap(center, hook_position) {
+                hook_transform.translation += if obstacle.kind.solid() {
+                    overlap.extend(0.)
+                } else {
+                    // Undo part of this tick's movement
+                    -velocity.extend(0.) * time.delta_secs() * (1. - obstacle.kind.drag())
+                };
+            }
+            if let Some(hitbox) = &hooked_fish
+                && hitbox.body_overlaps(obstacle.rect(center))
+            {
+                hook.tension += obstacle.kind.snag() * time.delta_secs();
+            }
+        }
+        if hook.tension >= hook.line_strength {
+            commands.trigger(HookLostEvent {
+                hook: entity,
+                player: owner.0,
+            });
+        }
+    }
+}
+
+/// Marks free fish whose body is behind an obstacle as `Sheltered`
+#[allow(clippy::type_complexity)]
+pub fn update_shelter(
+    fish_query: Query<(Entity, &Transform, &Fish, Has<Sheltered>), Without<HookedBy>>,
+    obstacles: Query<(&Transform, &Obstacle), Without<Fish>>,
+    grid: Res<grid::SpatialGrid>,
+    species_assets: Res<Assets<Species>>,
+    mut commands: Commands,
+) {
+    for (entity, fish_transform, fish, was_sheltered) in fish_query {
+        let Some(species) = species_assets.get(fish.species()) else {
+            continue;
+        };
+        let position = fish_transform.translation.truncate();
+        let hitbox = fish.hitbox(species, position);
+        let center = hitbox.body_from.midpoint(hitbox.body_to);
+        let sheltered =
+            obstacles
+                .iter_many(grid.query(center, 0.))
+                .any(|(obstacle_transform, obstacle)| {
+                    obstacle
+                        .rect(obstacle_transform.translation.truncate())
+                        .contains(center)
+                });
+        if sheltered && !was_sheltered {
+            commands.entity(entity).insert(Sheltered);
+        } else if !sheltered && was_sheltered {
+            commands.entity(entity).remove::<Sheltered>();
         }
     }
 }
@@ -1,85 +1,109 @@
 //! Visuals for fisherman and the rod
-use super::{PlayerOwns, hook::Hook};
+use super::{Player, PlayerOwns, hook::Hook};
 use crate::{
-    environment::fish::{Fish, FishExtractedEvent},
-    game_manager::{config::Config, scenes::SceneVolatile},
+    environment::{
+        boss::{Boss, BossEncounter},
+        fish::{Fish, FishExtractedEvent, HookedBy},
+        species::Species,
+    },
+    game_manager::{config::Config, scenes::StageClearScreen, state::InGame},
     prelude::GameState,
     utils::ui::ScoreDisplay,
 };
 use bevy::prelude::*;
-use bevy_prototype_lyon::prelude::*;
 use std::f32::consts::PI;
 
 /// Fisherman visual representing the player
 #[derive(Component)]
-#[require(PlayerOwns)]
+#[require(Player)]
 pub struct Fisherman;
 
 /// Visual rod that follows the hook
 #[derive(Component)]
-#[require(SceneVolatile)]
+#[require(DespawnOnExit<InGame> = DespawnOnExit(InGame))]
 pub struct Rod;
 
-#[derive(Component)]
-#[require(SceneVolatile)]
-pub struct FishingLine;
-
-/// Makes fisherman follow the hook visually
+/// Makes each fisherman and their rod follow their hook visually
+#[allow(clippy::type_complexity)]
 pub fn follow_hook(
-    guy: Single<(&mut Sprite, &Transform), With<Fisherman>>,
-    rod: Single<&mut Transform, (With<Rod>, Without<Hook>, Without<Fisherman>)>,
-    line: Single<&mut Shape, With<FishingLine>>,
-    hook_transform: Single<&Transform, With<Hook>>,
-    config: Res<Config>,
+    fishermen: Query<(&mut Sprite, &Transform, &PlayerOwns), With<Fisherman>>,
+    mut rods: Query<&mut Transform, (With<Rod>, Without<Hook>, Without<Fisherman>)>,
+    hooks: Query<&Transform, With<Hook>>,
 ) {
     // How far the hook must be for the rod to be fully extended
     const ROD_EXTEND: f32 = 64.;
 
-    let hook_transform = hook_transform.into_inner();
+    for (mut guy_sprite, guy_transform, owns) in fishermen {
+        let Some(hook_transform) = owns.iter().find_map(|entity| hooks.get(entity).ok()) else {
+            continue;
+        };
+        let Some(rod) = owns.iter().find(|&entity| rods.contains(entity)) else {
+            continue;
+        };
+        let Ok(mut rod_transform) = rods.get_mut(rod) else {
+            continue;
+        };
 
-    // Make guy face towards hook
-    let (mut guy_sprite, guy_transform) = guy.into_inner();
-    guy_sprite.flip_x = hook_transform.translation.x < guy_transform.translation.x;
+        // Make guy face towards hook
+        guy_sprite.flip_x = hook_transform.translation.x < guy_transform.translation.x;
 
-    // Rotate rod towards hook
-    let mut rod_transform = rod.into_inner();
-    let hook_rod_dist =
-        (hook_transform.translation.x - rod_transform.translation.x).clamp(-ROD_EXTEND, ROD_EXTEND);
-    let rod_rot = (PI / 2.) + (PI / 2.) * ops::sin((PI * hook_rod_dist) / (2. * ROD_EXTEND));
-    rod_transform.rotation = Quat::from_euler(EulerRot::XYZ, 0., rod_rot, 0.);
-
-    // Update fishing line
-    let local_offset = Transform::from_xyz(-32., 32., 0.).to_matrix();
-    let new_line = (
-        // Get end of rod
-        Transform::from_matrix(rod_transform.to_matrix() * local_offset)
-            .translation
-            .xy(),
-        // Get hook position
-        hook_transform.translation.xy() + Vec2::new(0., 8.),
-    );
-    let new_line = shapes::Line(new_line.0, new_line.1);
-    *line.into_inner() = ShapeBuilder::with(&new_line)
-        .stroke((Color::WHITE, config.visuals.line_width))
-        .build();
+        // Rotate rod towards hook
+        let hook_rod_dist = (hook_transform.translation.x - rod_transform.translation.x)
+            .clamp(-ROD_EXTEND, ROD_EXTEND);
+        let rod_rot = (PI / 2.) + (PI / 2.) * ops::sin((PI * hook_rod_dist) / (2. * ROD_EXTEND));
+        rod_transform.rotation = Quat::from_euler(EulerRot::XYZ, 0., rod_rot, 0.);
+    }
 }
 
+/// Credits a landed fish to the player who reeled it in, along with the
+/// stage's boss reward if it was the boss. Fish landed once the stage is
+/// cleared no longer count.
+#[allow(clippy::too_many_arguments)]
 pub fn on_extraction(
     event: On<FishExtractedEvent>,
     mut commands: Commands,
     mut state: ResMut<GameState>,
-    fish_query: Query<&Fish>,
+    mut encounter: ResMut<BossEncounter>,
+    clear_screen: Query<(), With<StageClearScreen>>,
+    fish_query: Query<(&Fish, &HookedBy, Has<Boss>)>,
+    mut hook_query: Query<(&mut Hook, &mut Transform)>,
+    players: Query<&Player>,
+    config: Res<Config>,
+    species_assets: Res<Assets<Species>>,
     mut score_display: Single<&mut Text, With<ScoreDisplay>>,
 ) {
-    let Ok(fish) = fish_query.get(event.fish) else {
+    let Ok((fish, hooked_by, is_boss)) = fish_query.get(event.fish) else {
         warn!(
             "Extracted fish {} does not exist in query. Was it removed too early?",
             event.fish
         );
         return;
     };
-    state.score += fish.get_score();
-    score_display.0 = format!("SCORE {:08}", state.score);
+    let player = players.get(event.player).map_or(0, |player| player.index);
+    if clear_screen.is_empty() {
+        if let Some(species) = species_assets.get(fish.species()) {
+            state.credit(player, fish.get_score(species));
+        } else {
+            warn!(
+                "Extracted fish {} has no loaded species, so it scores nothing",
+                event.fish
+            );
+        }
+        if is_boss && let Some(boss) = &state.cur_stage(&config).boss {
+            state.credit(player, boss.reward);
+            encounter.landed = true;
+        }
+        if fish.weight().0 > state.biggest_fish.0 {
+            state.biggest_fish = fish.weight().clone();
+        }
+        score_display.0 = state.score_text();
+    }
     state.fish_count -= 1;
     commands.entity(event.fish).despawn();
+
+    // Bring the hook back to the boat to cast for another fish
+    if let Ok((mut hook, mut transform)) = hook_query.get_mut(hooked_by.0) {
+        let x = Player::boat_x(player, state.players.len());
+        hook.reel_in(&mut commands, hooked_by.0, &mut transform, &config, x);
+    }
 }
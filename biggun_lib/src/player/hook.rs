@@ -1,114 +1,329 @@
 //! Movable hook and all related player components
 
 use crate::{
-    environment::fish::{self, Fish, FishExtractedEvent, HookedBy},
+    environment::{
+        fish::{Fish, FishExtractedEvent, HookedBy},
+        species::Species,
+    },
     game_manager::{
         config::Config,
-        scenes::SceneVolatile,
-        state::{GameOverEvent, GameState},
+        replay::{PlayerInput, RunInput},
+        state::{GameState, InGame},
     },
-    physics::Velocity,
-    player::OwnedByPlayer,
-    utils::{layers::Layer, ui::ScoreDisplay},
+    physics::{Drifts, Velocity},
+    player::{OwnedByPlayer, Player, lure::Lure},
+    utils::{layers::Layer, ui::TensionMeter},
 };
 
 use bevy::prelude::*;
 
 /// Controllable hook when fishing
 #[derive(Component)]
-#[require(SceneVolatile)]
+#[require(DespawnOnExit<InGame> = DespawnOnExit(InGame), Lure)]
 pub struct Hook {
     /// How fast you can move the hook horizontally
     pub speed: f32,
     /// How fast you can pull a fish upwards
     pub reel_speed: f32,
-    /// How fast the hook will fall down in the water
+    /// How fast the hook will fall down in the water without a lure
     pub density: f32,
     /// True when a fish is on the hook
     pub hooked: bool,
     /// How close a fish's anchor (mouth) must be to the hook in order to catch
     pub catch_radius: f32,
+    /// Strain on the line from fighting a hooked fish
+    pub tension: f32,
+    /// Tension at which the line breaks
+    pub line_strength: f32,
+    /// How much tension is lost per second while the line is slack
+    pub slack_recovery: f32,
+    /// How far a fully charged cast throws the hook
+    pub cast_range: f32,
+    pub cast: Cast,
+}
+
+/// Where a hook is in casting. Each attempt starts with the hook at the boat,
+/// where holding reel charges a cast and releasing it throws the hook.
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub enum Cast {
+    /// Brought back to the boat while reel is still held, e.g. from landing
+    /// a fish. Ready once reel is let go, so that it takes a fresh press to
+    /// charge the next cast.
+    Returned,
+    /// Waiting at the boat
+    #[default]
+    Ready,
+    /// Reel is held. `power` goes from 0 to 1 over `Hook::CHARGE_TIME` and
+    /// `facing` is -1 for left, 1 for right and 0 until the player steers.
+    Charging { power: f32, facing: f32 },
+    /// Thrown and arcing through the air
+    Flying,
+    /// In the water and fishing
+    Fishing,
 }
 
 #[derive(Component)]
 #[relationship_target(relationship = HookedBy)]
 pub struct HookedObjects(Vec<Entity>);
 
+/// A hooked fish got away from `player`'s `hook`, by breaking the line or
+/// swimming out of the lake
 #[derive(Event)]
-pub struct HookLostEvent;
+pub struct HookLostEvent {
+    pub hook: Entity,
+    pub player: Entity,
+}
 
 impl Hook {
-    pub fn start_pos(config: &Config) -> Vec3 {
-        Vec3::new(0.0, config.water_level, Layer::HOOK)
+    /// Seconds reel must be held for a fully charged cast
+    pub const CHARGE_TIME: f32 = 1.;
+    /// Pulls a cast hook back down to the water
+    const CAST_GRAVITY: f32 = 200.;
+
+    /// Where a hook cast from `x` starts
+    pub fn start_pos(config: &Config, x: f32) -> Vec3 {
+        Vec3::new(x, config.water_level, Layer::HOOK)
+    }
+
+    /// True once the hook has landed in the water and can catch fish
+    pub fn in_water(&self) -> bool {
+        self.cast == Cast::Fishing
+    }
+
+    /// How far a cast charged to `power` throws the hook
+    pub fn cast_distance(&self, power: f32) -> f32 {
+        self.cast_range * power.clamp(0., 1.)
+    }
+
+    /// Brings the hook back to the boat at `x`, ready to cast again
+    pub fn reel_in(
+        &mut self,
+        commands: &mut Commands,
+        entity: Entity,
+        transform: &mut Transform,
+        config: &Config,
+        x: f32,
+    ) {
+        self.hooked = false;
+        self.tension = 0.;
+        self.cast = Cast::Returned;
+        transform.translation = Hook::start_pos(config, x);
+        // Out of the water until the next cast lands
+        commands.entity(entity).remove::<Drifts>();
+    }
+
+    /// Moves the hook through a cast for one tick of `input`. Returns true
+    /// once the hook is fishing and takes regular input.
+    #[allow(clippy::too_many_arguments)]
+    fn update_cast(
+        &mut self,
+        commands: &mut Commands,
+        entity: Entity,
+        transform: &Transform,
+        velocity: &mut Velocity,
+        input: PlayerInput,
+        config: &Config,
+        secs: f32,
+    ) -> bool {
+        match self.cast {
+            Cast::Returned => {
+                velocity.0 = Vec2::ZERO;
+                if !input.reel {
+                    self.cast = Cast::Ready;
+                }
+                false
+            }
+            Cast::Ready | Cast::Charging { .. } => {
+                velocity.0 = Vec2::ZERO;
+                let (power, facing) = match self.cast {
+                    Cast::Charging { power, facing } => (power, facing),
+                    _ => (0., 0.),
+                };
+                let facing = if input.steer != 0. {
+                    input.steer.signum()
+                } else {
+                    facing
+                };
+                if input.reel {
+                    self.cast = Cast::Charging {
+                        power: (power + secs / Hook::CHARGE_TIME).min(1.),
+                        facing,
+                    };
+                } else if self.cast != Cast::Ready {
+                    // Without steering, throw out towards the middle of the
+                    // lake
+                    let x = transform.translation.x;
+                    let facing = if facing != 0. {
+                        facing
+                    } else if x > 0. {
+                        -1.
+                    } else {
+                        1.
+                    };
+                    // Land no further than the edge of the lake
+                    let room = (config.game_width - x * facing).max(0.);
+                    let distance = self.cast_distance(power).min(room);
+                    // Thrown at 45 degrees to land `distance` away
+                    let speed = ops::sqrt(Hook::CAST_GRAVITY * distance / 2.);
+                    velocity.0 = Vec2::new(facing * speed, speed);
+                    self.cast = Cast::Flying;
+                }
+                false
+            }
+            Cast::Flying => {
+                if transform.translation.y < config.water_level && velocity.y <= 0. {
+                    self.cast = Cast::Fishing;
+                    commands.entity(entity).insert(Drifts);
+                    return true;
+                }
+                velocity.y -= Hook::CAST_GRAVITY * secs;
+                false
+            }
+            Cast::Fishing => true,
+        }
     }
 }
 
-/// Adjusts the hook's velocity according to user input
+/// Adjusts each hook's velocity according to its player's input this tick,
+/// casting it first if it is still at the boat. Fighting a hooked fish builds
+/// tension, which breaks the line once it reaches `Hook::line_strength`.
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::type_complexity)]
 pub fn handle_input(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    hook: Single<(&mut Velocity, &Transform, &Hook)>,
-    hooked_fish: Option<Single<&Fish, With<fish::HookedBy>>>,
+    mut commands: Commands,
+    input: Res<RunInput>,
+    hooks: Query<(
+        Entity,
+        &mut Velocity,
+        &Transform,
+        &mut Hook,
+        &Lure,
+        &OwnedByPlayer,
+        Option<&HookedObjects>,
+    )>,
+    players: Query<&Player>,
+    mut fish_query: Query<&mut Fish>,
+    species_assets: Res<Assets<Species>>,
     config: Res<Config>,
     state: Res<GameState>,
+    time: Res<Time>,
 ) {
-    if !state.started {
-        return;
-    }
+    let upper_bound = config.water_level;
+    let lower_bound = config.water_level - state.cur_stage(&config).water_depth;
 
-    let (mut velocity, transform, hook) = hook.into_inner();
+    for (entity, mut velocity, transform, mut hook, lure, owner, hooked_objects) in hooks {
+        let Ok(player) = players.get(owner.0) else {
+            continue;
+        };
+        let input = input.players[player.index];
+        let reeling = input.reel;
+        if !hook.update_cast(
+            &mut commands,
+            entity,
+            transform,
+            &mut velocity,
+            input,
+            &config,
+            time.delta_secs(),
+        ) {
+            continue;
+        }
 
-    // Set initial horizontal velocity from keyboard input
-    velocity.0 = Vec2::new(0., 0.);
-    if keyboard_input.pressed(KeyCode::KeyA) {
-        velocity.x -= hook.speed;
-    }
-    if keyboard_input.pressed(KeyCode::KeyD) {
-        velocity.x += hook.speed;
-    }
+        // Set initial horizontal velocity from input
+        velocity.0 = Vec2::new(input.steer * hook.speed, 0.);
 
-    let mut vertical_resistance = 1.;
+        let mut vertical_resistance = 1.;
+        let mut strain = 0.;
 
-    let upper_bound = config.water_level;
-    let lower_bound = config.water_level - state.cur_stage(&config).water_depth;
-    // Have fish pull on hook if hooked
-    if let Some(hooked_fish) = hooked_fish {
-        // You can reel easier if you're not pulling in a direction
-        if velocity.x.abs() > 0.5 {
-            vertical_resistance = 4.;
+        // Have fish pull on hook if hooked
+        let hooked_fish = hooked_objects
+            .and_then(|objects| objects.iter().find(|&fish| fish_query.contains(fish)));
+        if let Some(mut hooked_fish) = hooked_fish.and_then(|fish| fish_query.get_mut(fish).ok())
+            && let Some(species) = species_assets.get(hooked_fish.species())
+        {
+            // You can reel easier if you're not pulling in a direction
+            if velocity.x.abs() > 0.5 {
+                vertical_resistance = 4.;
+            }
+            strain = hooked_fish.get_tension(species, &velocity.0, reeling);
+            velocity.x = hooked_fish.get_hook_velocity(species, &velocity.0);
+            // Fighting the reel wears the fish down; slack lets it catch its breath
+            if strain > 0. {
+                hooked_fish.tire(time.delta_secs());
+            } else {
+                hooked_fish.recover(species, time.delta_secs());
+            }
+        }
+        velocity.y = if reeling {
+            hook.reel_speed / vertical_resistance
+        } else {
+            -lure.config(&config).map_or(hook.density, |lure| {
+                lure.sink_speed(&config, transform.translation.y)
+            })
+        };
+        if (transform.translation.y > upper_bound && velocity.y > 0.)
+            || (transform.translation.y < lower_bound && velocity.y < 0.)
+        {
+            velocity.y = 0.;
+        }
+
+        hook.tension = if strain > 0. {
+            hook.tension + strain * time.delta_secs()
+        } else {
+            (hook.tension - hook.slack_recovery * time.delta_secs()).max(0.)
+        };
+        if hook.tension >= hook.line_strength {
+            commands.trigger(HookLostEvent {
+                hook: entity,
+                player: owner.0,
+            });
         }
-        velocity.x = hooked_fish.get_hook_velocity(hook, &velocity.0);
     }
-    velocity.y = if keyboard_input.pressed(KeyCode::Space) {
-        hook.reel_speed / vertical_resistance
-    } else {
-        -hook.density
-    };
-    if transform.translation.y > upper_bound && velocity.y > 0. {
-        velocity.y = 0.;
-    } else if transform.translation.y < lower_bound && velocity.y < 0. {
-        velocity.y = 0.;
+}
+
+/// Fills each player's HUD tension meter, turning it red as their line nears
+/// breaking. While charging a cast the meter shows its power instead.
+pub fn update_tension_meter(
+    hooks: Query<(&Hook, &OwnedByPlayer)>,
+    meters: Query<(&OwnedByPlayer, &mut Node, &mut BackgroundColor), With<TensionMeter>>,
+) {
+    for (owner, mut node, mut color) in meters {
+        let Some((hook, _)) = hooks.iter().find(|(_, hook_owner)| hook_owner.0 == owner.0) else {
+            continue;
+        };
+        if let Cast::Charging { power, .. } = hook.cast {
+            node.width = percent(power * 100.);
+            color.0 = Color::srgb(0.3, 0.8, 1.);
+            continue;
+        }
+        let fill = (hook.tension / hook.line_strength).clamp(0., 1.);
+        node.width = percent(fill * 100.);
+        color.0 = Color::WHITE.mix(&Color::srgb(1., 0., 0.), fill);
     }
 }
 
-/// Extracts a hooked fish when the hook reaches the surface and adds to score
+/// Extracts hooked fish when their hook reaches the surface, crediting the
+/// hook's player
 pub fn check_extraction(
     mut commands: Commands,
-    hook_transform: Single<(&Transform, &OwnedByPlayer, &Hook), Without<HookedBy>>,
-    hooked_fish: Single<Entity, With<HookedBy>>,
+    hooks: Query<(&Transform, &OwnedByPlayer, &HookedObjects), With<Hook>>,
     config: Res<Config>,
 ) {
     // How close the hook mut be to the surface of the water to register the
     // extraction
     const SURFACE_DIST: f32 = 0.1;
-    let fish_entity = hooked_fish.into_inner();
-    let (hook_transform, player, _) = hook_transform.into_inner();
 
-    if hook_transform.translation.y >= config.water_level - SURFACE_DIST {
+    for (hook_transform, player, hooked_objects) in hooks {
+        if hook_transform.translation.y < config.water_level - SURFACE_DIST {
+            continue;
+        }
         // Extraction has occured
-        commands.trigger(FishExtractedEvent {
-            fish: fish_entity,
-            player: player.0,
-        });
+        for fish in hooked_objects.iter() {
+            commands.trigger(FishExtractedEvent {
+                fish,
+                player: player.0,
+            });
+        }
     }
 }
 
@@ -157,6 +372,28 @@ pub fn on_hook_event(
     }
 }
 
-pub fn on_hook_lost(_: On<HookLostEvent>, mut commands: Commands) {
-    commands.trigger(GameOverEvent);
+/// Loses whatever the hook had caught and brings it back to its player's spot
+/// in the boat to cast again. Other players keep fishing and the run carries
+/// on.
+pub fn on_hook_lost(
+    event: On<HookLostEvent>,
+    mut commands: Commands,
+    mut hooks: Query<(&mut Hook, &mut Transform, Option<&HookedObjects>)>,
+    players: Query<&Player>,
+    mut state: ResMut<GameState>,
+    config: Res<Config>,
+) {
+    let Ok((mut hook, mut transform, hooked_objects)) = hooks.get_mut(event.hook) else {
+        return;
+    };
+    for fish in hooked_objects
+        .into_iter()
+        .flat_map(|objects| objects.iter())
+    {
+        commands.entity(fish).despawn();
+        state.fish_count -= 1;
+    }
+    let index = players.get(event.player).map_or(0, |player| player.index);
+    let x = Player::boat_x(index, state.players.len());
+    hook.reel_in(&mut commands, event.hook, &mut transform, &config, x);
 }
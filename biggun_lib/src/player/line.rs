@@ -0,0 +1,200 @@
+//! The fishing line, simulated as a rope: a chain of points kept a fixed
+//! distance apart and moved with Verlet integration. It sags while slack,
+//! pulls straight under tension and drapes over obstacles.
+
+use super::{OwnedByPlayer, PlayerOwns, fisherman::Rod, hook::Hook};
+use crate::{
+    environment::obstacle::Obstacle,
+    game_manager::{config::Config, state::GameState, state::InGame},
+    physics::Velocity,
+};
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+
+/// Line from a player's rod tip to their hook
+#[derive(Component, Default)]
+#[require(DespawnOnExit<InGame> = DespawnOnExit(InGame))]
+pub struct FishingLine {
+    /// In world space, from the rod tip to the hook
+    points: Vec<Vec2>,
+    /// Where `points` were last step, which gives their velocity
+    previous: Vec<Vec2>,
+    /// How much line is paid out
+    length: f32,
+}
+
+impl FishingLine {
+    const SEGMENTS: usize = 24;
+    /// Passes made over the segments each step to pull them back to length
+    const ITERATIONS: usize = 16;
+    /// Line paid out beyond the straight distance to the hook while fully
+    /// slack, relative to that distance
+    const MAX_SLACK: f32 = 0.3;
+    /// How quickly line is paid out to match the slack, per second
+    const PAY_OUT_RATE: f32 = 4.;
+    const AIR_GRAVITY: f32 = 160.;
+    const WATER_GRAVITY: f32 = 25.;
+    /// Fraction of their speed points keep each step
+    const AIR_DAMPING: f32 = 0.99;
+    const WATER_DAMPING: f32 = 0.85;
+    /// Hooks moving further than this in one step were moved, not pulled,
+    /// and the line starts over
+    const SNAP_DISTANCE: f32 = 32.;
+
+    /// Lays the line out straight between `from` and `to`
+    fn reset(&mut self, from: Vec2, to: Vec2) {
+        self.points = (0..=Self::SEGMENTS)
+            .map(|i| from.lerp(to, i as f32 / Self::SEGMENTS as f32))
+            .collect();
+        self.previous = self.points.clone();
+        self.length = from.distance(to);
+    }
+
+    /// Advances the line by `secs` with its ends at `from` and `to`. `slack`
+    /// goes from 0 for a taut line to 1 for one paid out freely. The line
+    /// rests on the lakebed and drapes over `solids`, given with their
+    /// centers.
+    #[allow(clippy::too_many_arguments)]
+    fn step(
+        &mut self,
+        from: Vec2,
+        to: Vec2,
+        slack: f32,
+        secs: f32,
+        config: &Config,
+        floor: f32,
+        solids: &[(Vec2, &Obstacle)],
+    ) {
+        let moved = self
+            .points
+            .last()
+            .is_none_or(|end| end.distance(to) > Self::SNAP_DISTANCE);
+        if self.points.len() != Self::SEGMENTS + 1 || moved {
+            self.reset(from, to);
+        }
+
+        let straight = from.distance(to);
+        let wanted = straight * (1. + Self::MAX_SLACK * slack.clamp(0., 1.));
+        // Line is reeled in at once but pays out gradually
+        let catch_up = 1. - ops::exp(-Self::PAY_OUT_RATE * secs);
+        self.length = self.length.lerp(wanted, catch_up).min(wanted).max(straight);
+        let rest = self.length / Self::SEGMENTS as f32;
+
+        for (point, previous) in self.points.iter_mut().zip(&mut self.previous) {
+            let (gravity, damping) = if point.y < config.water_level {
+                (Self::WATER_GRAVITY, Self::WATER_DAMPING)
+            } else {
+                (Self::AIR_GRAVITY, Self::AIR_DAMPING)
+            };
+            let velocity = (*point - *previous) * damping;
+            *previous = *point;
+            *point += velocity - Vec2::Y * gravity * secs * secs;
+        }
+
+        let last = Self::SEGMENTS;
+        for _ in 0..Self::ITERATIONS {
+            self.points[0] = from;
+            self.points[last] = to;
+            for i in 0..last {
+                let (a, b) = (self.points[i], self.points[i + 1]);
+                let distance = a.distance(b);
+                if distance <= f32::EPSILON {
+                    continue;
+                }
+                let correction = (b - a) * (distance - rest) / distance;
+                // Ends are held by the rod and hook, so the free point moves
+                // all the way
+                match (i == 0, i + 1 == last) {
+                    (true, _) => self.points[i + 1] -= correction,
+                    (_, true) => self.points[i] += correction,
+                    _ => {
+                        self.points[i] += correction / 2.;
+                        self.points[i + 1] -= correction / 2.;
+                    }
+                }
+            }
+            for point in &mut self.points[1..last] {
+                point.y = point.y.max(floor);
+                for &(center, obstacle) in solids {
+                    if let Some(push) = obstacle.overlap(center, *point) {
+                        *point += push;
+                    }
+                }
+            }
+        }
+        self.points[0] = from;
+        self.points[last] = to;
+    }
+
+    /// The line as a lyon path
+    fn path(&self) -> ShapePath {
+        let mut points = self.points.iter();
+        let start = points.next().copied().unwrap_or_default();
+        points.fold(ShapePath::new().move_to(start), |path, &point| {
+            path.line_to(point)
+        })
+    }
+}
+
+/// Gets the end of a rod in world space
+pub fn rod_tip(rod_transform: &Transform) -> Vec2 {
+    let local_offset = Transform::from_xyz(-32., 32., 0.).to_matrix();
+    Transform::from_matrix(rod_transform.to_matrix() * local_offset)
+        .translation
+        .xy()
+}
+
+/// Simulates each player's line between their rod and hook, and redraws it.
+/// Lines pull taut while reeling or under tension and sag while the hook
+/// sinks freely.
+#[allow(clippy::too_many_arguments)]
+pub fn update_lines(
+    lines: Query<(&mut FishingLine, &mut Shape, &OwnedByPlayer)>,
+    owners: Query<&PlayerOwns>,
+    rods: Query<&Transform, With<Rod>>,
+    hooks: Query<(&Transform, &Hook, &Velocity)>,
+    obstacles: Query<(&Transform, &Obstacle)>,
+    config: Res<Config>,
+    state: Res<GameState>,
+    time: Res<Time>,
+) {
+    // Long frames would fling the line about
+    let secs = time.delta_secs().min(1. / 30.);
+    let floor = config.water_level - state.cur_stage(&config).water_depth;
+    let solids: Vec<_> = obstacles
+        .iter()
+        .filter(|(_, obstacle)| obstacle.kind.solid())
+        .map(|(transform, obstacle)| (transform.translation.truncate(), obstacle))
+        .collect();
+
+    for (mut line, mut shape, owner) in lines {
+        let Ok(owns) = owners.get(owner.0) else {
+            continue;
+        };
+        let Some(rod_transform) = owns.iter().find_map(|entity| rods.get(entity).ok()) else {
+            continue;
+        };
+        let Some((hook_transform, hook, velocity)) =
+            owns.iter().find_map(|entity| hooks.get(entity).ok())
+        else {
+            continue;
+        };
+
+        let reeling = velocity.y > 0.;
+        let tension = hook.tension / hook.line_strength;
+        let slack = (1. - tension) * if reeling { 0.1 } else { 1. };
+        let hook_eye = hook_transform.translation.xy() + Vec2::new(0., 8.);
+        line.step(
+            rod_tip(rod_transform),
+            hook_eye,
+            slack,
+            secs,
+            &config,
+            floor,
+            &solids,
+        );
+        *shape = ShapeBuilder::with(&line.path())
+            .stroke((Color::WHITE, config.visuals.line_width))
+            .build();
+    }
+}
@@ -0,0 +1,191 @@
+//! Bait and lures tied onto the hook. Each sinks its own way and draws fish
+//! from its own distance, and some species go for certain lures more readily.
+//! Players pick their lure at the boat before casting.
+
+use super::{
+    OwnedByPlayer, Player,
+    hook::{Cast, Hook},
+};
+use crate::{
+    environment::species::{Species, SpeciesLibrary},
+    game_manager::{config::Config, replay::RunInput},
+    utils::ui::LureDisplay,
+};
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// How a lure settles in the water
+#[derive(Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+pub enum LureDepth {
+    /// Keeps sinking until it reaches the lakebed
+    #[default]
+    Sinks,
+    /// Stops sinking this far below the surface
+    Suspends(f32),
+}
+
+/// A lure or bait players can fish with
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LureConfig {
+    /// Shown in the HUD
+    pub name: String,
+    /// How close a fish must come to notice the lure
+    pub attraction_radius: f32,
+    /// Chance from 0 to 1 that a fish noticing the lure goes for it
+    pub appeal: f32,
+    /// Species, by the same paths as spawn tables, that go for the lure twice
+    /// as readily
+    #[serde(default)]
+    pub preferred: Vec<String>,
+    #[serde(default)]
+    pub depth: LureDepth,
+    /// How fast the hook sinks with this lure on, in place of `Hook::density`
+    pub sink_rate: f32,
+}
+
+impl LureConfig {
+    /// Chance a fish of `species` noticing the lure goes for it
+    pub fn appeal_to(&self, library: &SpeciesLibrary, species: &Handle<Species>) -> f32 {
+        let preferred = self
+            .preferred
+            .iter()
+            .any(|path| library.get(path) == Some(species));
+        if preferred {
+            (self.appeal * 2.).min(1.)
+        } else {
+            self.appeal
+        }
+    }
+
+    /// How fast a hook at height `y` sinks with this lure on
+    pub fn sink_speed(&self, config: &Config, y: f32) -> f32 {
+        match self.depth {
+            LureDepth::Sinks => self.sink_rate,
+            LureDepth::Suspends(depth) if y > config.water_level - depth => self.sink_rate,
+            LureDepth::Suspends(_) => 0.,
+        }
+    }
+}
+
+/// The lure on a hook, one of `Config::lures`
+#[derive(Component, Default)]
+#[require(LureMotion)]
+pub struct Lure {
+    /// Index into `Config::lures`
+    pub index: usize,
+    /// Which way the player steered last tick, so that holding a direction
+    /// only switches lures once
+    steering: f32,
+}
+
+impl Lure {
+    /// The lure's settings, or `None` when no lures are configured and fish
+    /// bite a bare hook on contact
+    pub fn config<'a>(&self, config: &'a Config) -> Option<&'a LureConfig> {
+        config.lures.get(self.index)
+    }
+}
+
+/// How the player has been working a lure lately, as fish watching it see
+/// it. Only presses of reel or steer count, never the lure sinking on its own,
+/// and motion is reset while the hook is out of the water.
+#[derive(Component, Default)]
+pub struct LureMotion {
+    /// Seconds the current press has been held, `None` while nothing is
+    /// pressed
+    held: Option<f32>,
+    /// Set for the tick a press short enough to be a twitch is let go
+    pub twitched: bool,
+    /// How often the lure is pressed, in presses per second smoothed over
+    /// `LureMotion::SMOOTHING`. A single press never reaches more than 1.
+    pub jerk: f32,
+}
+
+impl LureMotion {
+    /// Seconds over which presses are averaged
+    const SMOOTHING: f32 = 1.;
+    /// Longest a press can be held, in seconds, to count as a twitch
+    const TWITCH_TIME: f32 = 0.3;
+}
+
+/// Follows how each player works their lure with this tick's input
+pub fn track_motion(
+    input: Res<RunInput>,
+    hooks: Query<(&Hook, &OwnedByPlayer, &mut LureMotion)>,
+    players: Query<&Player>,
+    time: Res<Time>,
+) {
+    let secs = time.delta_secs();
+    let blend = 1. - ops::exp(-secs / LureMotion::SMOOTHING);
+    for (hook, owner, mut motion) in hooks {
+        let Ok(player) = players.get(owner.0) else {
+            continue;
+        };
+        let input = input.players[player.index];
+        let pressed = input.reel || input.steer.round() != 0.;
+        if !hook.in_water() {
+            // A press carried over from casting isn't working the lure
+            *motion = LureMotion {
+                held: pressed.then_some(f32::INFINITY),
+                ..default()
+            };
+            continue;
+        }
+        let started = pressed && motion.held.is_none();
+        motion.twitched = !pressed
+            && motion
+                .held
+                .is_some_and(|held| held <= LureMotion::TWITCH_TIME);
+        motion.held = pressed.then(|| motion.held.map_or(0., |held| held + secs));
+        let rate = if started { 1. / secs } else { 0. };
+        motion.jerk = motion.jerk.lerp(rate, blend);
+    }
+}
+
+/// Switches lures while hooks wait at the boat, one lure for each press of
+/// left or right
+pub fn choose_lure(
+    input: Res<RunInput>,
+    hooks: Query<(&Hook, &mut Lure, &OwnedByPlayer)>,
+    players: Query<&Player>,
+    config: Res<Config>,
+) {
+    let count = config.lures.len() as isize;
+    for (hook, mut lure, owner) in hooks {
+        let Ok(player) = players.get(owner.0) else {
+            continue;
+        };
+        // -1, 0 or 1, with sticks counting once pushed halfway
+        let steering = input.players[player.index].steer.round();
+        let pressed = steering != 0. && steering != lure.steering;
+        lure.steering = steering;
+        if hook.cast != Cast::Ready || !pressed || count == 0 {
+            continue;
+        }
+        lure.index = (lure.index as isize + steering as isize).rem_euclid(count) as usize;
+    }
+}
+
+/// Names the lure on each player's hook in the HUD, marked as switchable
+/// while the hook is at the boat
+pub fn update_lure_display(
+    hooks: Query<(&Hook, &Lure, &OwnedByPlayer)>,
+    displays: Query<(&mut Text, &OwnedByPlayer), With<LureDisplay>>,
+    config: Res<Config>,
+) {
+    for (mut text, owner) in displays {
+        let Some((hook, lure, _)) = hooks
+            .iter()
+            .find(|(_, _, hook_owner)| hook_owner.0 == owner.0)
+        else {
+            continue;
+        };
+        let name = lure.config(&config).map_or("", |lure| &lure.name);
+        text.0 = if hook.cast == Cast::Ready && config.lures.len() > 1 {
+            format!("< {name} >")
+        } else {
+            name.to_string()
+        };
+    }
+}
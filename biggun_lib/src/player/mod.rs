@@ -1,11 +1,39 @@
+use crate::game_manager::{
+    TickSet,
+    state::{self, AppState, InGame},
+};
 use bevy::prelude::*;
 
 pub(crate) mod fisherman;
 pub(crate) mod hook;
+pub(crate) mod line;
+pub(crate) mod lure;
 
 /// Handles player actions.
 pub struct BiggunPlayerPlugin;
 
+/// A player taking part in the game. Kept separate from `PlayerOwns`, which is
+/// removed whenever the player owns nothing (e.g. between runs).
+#[derive(Component, Default)]
+pub struct Player {
+    /// Which player this is, counting from 0. Picks the player's controls and
+    /// their spot in the boat.
+    pub index: usize,
+}
+
+impl Player {
+    /// Most players that can share the lake
+    pub const MAX: usize = 4;
+    /// Horizontal distance between players standing in the boat
+    const SPACING: f32 = 16.;
+
+    /// Where player `index` of `count` stands in the boat. A lone player
+    /// stands in the middle.
+    pub fn boat_x(index: usize, count: usize) -> f32 {
+        (index as f32 - (count.max(1) - 1) as f32 / 2.) * Player::SPACING
+    }
+}
+
 /// Adds functionality or decoration to a player
 #[derive(Component)]
 #[relationship(relationship_target = PlayerOwns)]
@@ -19,10 +47,32 @@ pub struct PlayerOwns(Vec<Entity>);
 
 impl Plugin for BiggunPlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (hook::handle_input, fisherman::follow_hook))
-            .add_systems(FixedUpdate, hook::check_extraction)
-            .add_observer(hook::on_hook_event)
-            .add_observer(hook::on_hook_lost)
-            .add_observer(fisherman::on_extraction);
+        app.add_systems(
+            Update,
+            (
+                (hook::update_tension_meter, lure::update_lure_display)
+                    .run_if(in_state(AppState::Playing)),
+                (
+                    fisherman::follow_hook.run_if(in_state(InGame)),
+                    line::update_lines.run_if(in_state(AppState::Playing)),
+                )
+                    .chain(),
+            ),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                (lure::choose_lure, hook::handle_input, lure::track_motion)
+                    .chain()
+                    .in_set(TickSet::Control),
+                hook::check_extraction
+                    .in_set(TickSet::Rules)
+                    .before(state::CountdownTimer::tick),
+            )
+                .run_if(in_state(AppState::Playing)),
+        )
+        .add_observer(hook::on_hook_event)
+        .add_observer(hook::on_hook_lost)
+        .add_observer(fisherman::on_extraction);
     }
 }
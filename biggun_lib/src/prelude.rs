@@ -1,8 +1,16 @@
 //! A collection of all plugins and needed types for the game's `main` function
 
 pub use crate::{
+    cli::arg_value,
     environment::BiggunEnvironmentPlugin,
-    game_manager::{BiggunGameManagerPlugin, config::Config, state::GameState},
+    game_manager::{
+        BiggunGameManagerPlugin,
+        config::Config,
+        replay::{Playback, Recorder, Replay},
+        rng::GameRng,
+        session::Session,
+        state::GameState,
+    },
     physics::BiggunPhysicsPlugin,
     player::BiggunPlayerPlugin,
 };
@@ -0,0 +1,187 @@
+//! Scripted players for the simulation harness
+
+use crate::{
+    environment::{
+        behaviour::Behaviour,
+        fish::{Fish, HookedBy},
+        obstacle::Sheltered,
+        species::Species,
+    },
+    game_manager::{
+        config::Config,
+        replay::{PlayerInput, RunInput},
+    },
+    physics::Velocity,
+    player::{
+        OwnedByPlayer, Player,
+        hook::{Cast, Hook},
+    },
+};
+use bevy::prelude::*;
+use std::{fmt, str::FromStr};
+
+/// How a bot plays
+#[derive(Resource, Clone, Copy, Debug)]
+pub enum BotPolicy {
+    /// Never touches the controls, so never even casts. A baseline for how
+    /// the stage plays out with no one fishing.
+    Idle,
+    /// Chases the nearest fish, passing up schools of baitfish while there
+    /// is anything bigger, and reels non-stop once it bites
+    Greedy,
+    /// Chases fish like `Greedy` but only reels while the line is below half
+    /// its strength, keeping the fish away from the edges
+    Patient,
+}
+
+impl BotPolicy {
+    pub const ALL: [BotPolicy; 3] = [Self::Idle, Self::Greedy, Self::Patient];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Idle => "idle",
+            Self::Greedy => "greedy",
+            Self::Patient => "patient",
+        }
+    }
+}
+
+impl fmt::Display for BotPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for BotPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<BotPolicy, String> {
+        BotPolicy::ALL
+            .into_iter()
+            .find(|policy| policy.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = BotPolicy::ALL.iter().map(BotPolicy::name).collect();
+                format!("unknown policy `{s}`, expected one of {}", names.join(", "))
+            })
+    }
+}
+
+/// Free fish a bot can see
+type VisibleFish<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Transform, &'static Velocity, &'static Fish),
+    (Without<HookedBy>, Without<Sheltered>),
+>;
+
+/// Replaces this tick's input with the bots', one bot for each player
+pub fn play(
+    policy: Res<BotPolicy>,
+    mut input: ResMut<RunInput>,
+    hooks: Query<(Entity, &Transform, &Hook, &OwnedByPlayer)>,
+    players: Query<&Player>,
+    fish: VisibleFish,
+    species_assets: Res<Assets<Species>>,
+    config: Res<Config>,
+) {
+    for (entity, hook_transform, hook, owner) in hooks {
+        let Ok(player) = players.get(owner.0) else {
+            continue;
+        };
+        input.players[player.index] = play_hook(
+            *policy,
+            entity,
+            hook_transform,
+            hook,
+            &fish,
+            &species_assets,
+            &config,
+        );
+    }
+}
+
+/// Input for a single bot fishing with `hook`
+#[allow(clippy::too_many_arguments)]
+fn play_hook(
+    policy: BotPolicy,
+    entity: Entity,
+    hook_transform: &Transform,
+    hook: &Hook,
+    fish: &VisibleFish,
+    species_assets: &Assets<Species>,
+    config: &Config,
+) -> PlayerInput {
+    // How far ahead of a fish bots leave their lure for it to find
+    const LEAD: f32 = 24.;
+
+    let hook_pos = hook_transform.translation.truncate();
+    let mut input = PlayerInput::default();
+    // Where to put the lure for the nearest fish to swim into it, and
+    // whether that fish is only baitfish
+    let target = fish
+        .iter()
+        .map(|(transform, velocity, fish)| {
+            let baitfish = species_assets
+                .get(fish.species())
+                .is_some_and(|species| species.school.is_some());
+            let lead = Vec2::new(velocity.x.signum() * LEAD, 0.);
+            (baitfish, transform.translation.truncate() + lead)
+        })
+        .min_by(|a, b| {
+            a.0.cmp(&b.0)
+                .then(a.1.distance(hook_pos).total_cmp(&b.1.distance(hook_pos)))
+        })
+        .map(|(_, target)| target);
+    // A fish already has its eye on the lure, so keep it still
+    let watched = fish.iter().any(|(_, _, fish)| {
+        fish.state.behaviour.hook() == Some(entity)
+            && !matches!(fish.state.behaviour, Behaviour::Ignore { .. })
+    });
+
+    match policy {
+        BotPolicy::Idle => {}
+        // Charges until the cast would land by the nearest fish. Steering
+        // only once charging, as it switches lures before.
+        _ if !hook.in_water() => {
+            let power = match hook.cast {
+                Cast::Charging { power, .. } => power,
+                _ => 0.,
+            };
+            if let Some(target) = target
+                && !matches!(hook.cast, Cast::Flying | Cast::Returned)
+            {
+                if hook.cast != Cast::Ready {
+                    steer_toward(&mut input, hook_pos.x, target.x, 0.);
+                }
+                input.reel =
+                    power < 1. && hook.cast_distance(power) < (target.x - hook_pos.x).abs();
+            }
+        }
+        _ if !hook.hooked && watched => {}
+        _ if !hook.hooked => {
+            if let Some(target) = target {
+                steer_toward(&mut input, hook_pos.x, target.x, hook.catch_radius);
+                // Reeling is the only way up, the hook sinks on its own
+                input.reel = hook_pos.y < target.y - hook.catch_radius;
+            }
+        }
+        BotPolicy::Greedy => input.reel = true,
+        BotPolicy::Patient => {
+            input.reel = hook.tension < hook.line_strength / 2.;
+            if hook_pos.x.abs() > config.game_width / 2. {
+                steer_toward(&mut input, hook_pos.x, 0., 0.);
+            }
+        }
+    }
+    input
+}
+
+fn steer_toward(input: &mut PlayerInput, from: f32, to: f32, tolerance: f32) {
+    input.steer = if to < from - tolerance {
+        -1.
+    } else if to > from + tolerance {
+        1.
+    } else {
+        0.
+    };
+}
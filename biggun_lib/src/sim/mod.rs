@@ -0,0 +1,334 @@
+//! Headless simulation harness for balancing species and stages. Bots play
+//! each configured stage many times without a window or rendering, and the
+//! results are collected into a [`Report`].
+
+use crate::{
+    environment::{
+        BiggunEnvironmentPlugin,
+        fish::FishExtractedEvent,
+        species::{Species, SpeciesLibrary},
+    },
+    game_manager::{
+        BiggunGameManagerPlugin, Persistence, TickSet,
+        config::Config,
+        replay,
+        rng::GameRng,
+        state::{AppState, GameOverEvent, GameState, NextStageEvent, StageClearEvent},
+    },
+    physics::BiggunPhysicsPlugin,
+    player::{
+        BiggunPlayerPlugin,
+        hook::{HookEvent, HookLostEvent},
+    },
+};
+use bevy::{
+    asset::LoadState, input::InputPlugin, prelude::*, state::app::StatesPlugin, text::TextPlugin,
+    time::TimeUpdateStrategy,
+};
+use std::{fmt, time::Duration};
+
+pub mod bots;
+pub mod stress;
+
+pub use bots::BotPolicy;
+
+/// How many ticks a single attempt may last before it is given up on, in
+/// case a stage has no time limit
+const MAX_ATTEMPT_TICKS: u32 = 64 * 60 * 10;
+
+/// How to run a simulation
+pub struct SimSettings {
+    /// Where `assets/` is, relative to the running crate
+    pub asset_root: String,
+    /// Attempts made at each stage
+    pub runs_per_stage: usize,
+    pub policy: BotPolicy,
+    /// Attempt `n` of every stage is seeded with `seed + n`
+    pub seed: u64,
+}
+
+/// How an attempt at a stage ended
+#[derive(Clone, Copy, PartialEq)]
+enum Outcome {
+    Cleared,
+    Failed,
+}
+
+/// What happened during the current attempt
+#[derive(Resource, Default)]
+struct Tracker {
+    tick: u32,
+    /// Tick the fish currently on the hook bit
+    hooked_at: Option<u32>,
+    hooked: u32,
+    landed: u32,
+    lost: u32,
+    /// Seconds from bite to landing for each fish landed
+    land_times: Vec<f32>,
+    outcome: Option<Outcome>,
+}
+
+/// Results of all attempts at one stage
+pub struct StageReport {
+    /// Counting from 1
+    pub stage: usize,
+    pub attempts: usize,
+    pub cleared: usize,
+    /// Fish that bit
+    pub hooked: u32,
+    /// Fish pulled out of the water
+    pub landed: u32,
+    /// Fish lost off the hook, by breaking the line or swimming away
+    pub lost: u32,
+    /// Seconds from bite to landing, sorted
+    pub land_times: Vec<f32>,
+    /// Score of each attempt, sorted
+    pub scores: Vec<u32>,
+}
+
+impl StageReport {
+    /// Fraction of bites that were landed
+    pub fn catch_rate(&self) -> f32 {
+        ratio(self.landed as usize, self.hooked as usize)
+    }
+
+    pub fn clear_rate(&self) -> f32 {
+        ratio(self.cleared, self.attempts)
+    }
+}
+
+fn ratio(count: usize, total: usize) -> f32 {
+    if total == 0 {
+        0.
+    } else {
+        count as f32 / total as f32
+    }
+}
+
+/// Gets the value at `fraction` through sorted `values`
+pub(crate) fn percentile<T: Copy + Default>(values: &[T], fraction: f32) -> T {
+    if values.is_empty() {
+        return T::default();
+    }
+    let index = ((values.len() - 1) as f32 * fraction).round() as usize;
+    values[index]
+}
+
+/// Results of a whole simulation
+pub struct Report {
+    pub policy: BotPolicy,
+    pub stages: Vec<StageReport>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "policy: {}", self.policy)?;
+        writeln!(
+            f,
+            "{:>5} {:>8} {:>7} {:>7} {:>7} {:>7} {:>7}   {:>17}   {:>23}",
+            "stage",
+            "attempts",
+            "clear",
+            "hooked",
+            "landed",
+            "lost",
+            "catch",
+            "land s p10/50/90",
+            "score p10/50/90"
+        )?;
+        for stage in &self.stages {
+            writeln!(
+                f,
+                "{:>5} {:>8} {:>6.1}% {:>7} {:>7} {:>7} {:>6.1}%   {:>5.1} {:>5.1} {:>5.1}   {:>7} {:>7} {:>7}",
+                stage.stage,
+                stage.attempts,
+                stage.clear_rate() * 100.,
+                stage.hooked,
+                stage.landed,
+                stage.lost,
+                stage.catch_rate() * 100.,
+                percentile(&stage.land_times, 0.1),
+                percentile(&stage.land_times, 0.5),
+                percentile(&stage.land_times, 0.9),
+                percentile(&stage.scores, 0.1),
+                percentile(&stage.scores, 0.5),
+                percentile(&stage.scores, 0.9),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Plays every stage in `config` `settings.runs_per_stage` times
+pub fn simulate(config: Config, settings: &SimSettings) -> Result<Report, String> {
+    let stage_count = config.stages.len();
+    let mut app = build_app(config, settings);
+    wait_for_species(&mut app)?;
+
+    let mut stages = Vec::with_capacity(stage_count);
+    for stage in 0..stage_count {
+        let mut report = StageReport {
+            stage: stage + 1,
+            attempts: settings.runs_per_stage,
+            cleared: 0,
+            hooked: 0,
+            landed: 0,
+            lost: 0,
+            land_times: Vec::new(),
+            scores: Vec::new(),
+        };
+        for attempt in 0..settings.runs_per_stage {
+            let seed = settings.seed.wrapping_add(attempt as u64);
+            let (tracker, score) = run_attempt(&mut app, stage, seed);
+            if tracker.outcome == Some(Outcome::Cleared) {
+                report.cleared += 1;
+            }
+            report.hooked += tracker.hooked;
+            report.landed += tracker.landed;
+            report.lost += tracker.lost;
+            report.land_times.extend(tracker.land_times);
+            report.scores.push(score);
+        }
+        report.land_times.sort_by(f32::total_cmp);
+        report.scores.sort();
+        stages.push(report);
+    }
+
+    Ok(Report {
+        policy: settings.policy,
+        stages,
+    })
+}
+
+pub(crate) fn build_app(config: Config, settings: &SimSettings) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin {
+            file_path: settings.asset_root.clone(),
+            ..default()
+        },
+        StatesPlugin,
+        InputPlugin,
+        ImagePlugin::default(),
+        TextPlugin,
+    ))
+    .add_plugins((
+        BiggunPlayerPlugin,
+        BiggunGameManagerPlugin,
+        BiggunPhysicsPlugin,
+        BiggunEnvironmentPlugin,
+    ))
+    // Every update advances exactly one fixed tick, as fast as possible
+    .insert_resource(TimeUpdateStrategy::ManualDuration(
+        Time::<Fixed>::default().timestep(),
+    ))
+    .insert_resource(Persistence { enabled: false })
+    .insert_resource(GameState::default())
+    .insert_resource(config)
+    .insert_resource(settings.policy)
+    .init_resource::<Tracker>()
+    .add_systems(
+        FixedUpdate,
+        (
+            count_tick.in_set(TickSet::Input),
+            bots::play
+                .in_set(TickSet::Input)
+                .after(replay::read_input)
+                .before(replay::restart_stage),
+        )
+            .run_if(in_state(AppState::Playing)),
+    )
+    .add_observer(on_hook)
+    .add_observer(on_extraction)
+    .add_observer(on_hook_lost)
+    .add_observer(on_stage_clear)
+    .add_observer(on_game_over);
+    app.finish();
+    app.cleanup();
+    app
+}
+
+/// Updates until every species used by the config has loaded
+pub(crate) fn wait_for_species(app: &mut App) -> Result<(), String> {
+    loop {
+        app.update();
+        let world = app.world();
+        let library = world.resource::<SpeciesLibrary>();
+        let asset_server = world.resource::<AssetServer>();
+        let mut loaded = true;
+        for (path, handle) in library.iter() {
+            match asset_server.load_state(handle) {
+                LoadState::Loaded => {}
+                LoadState::Failed(e) => return Err(format!("could not load {path}: {e}")),
+                _ => loaded = false,
+            }
+        }
+        if loaded && world.resource::<Assets<Species>>().len() >= library.iter().count() {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Plays `stage` once from the main menu, returning what happened and the
+/// stage's score
+fn run_attempt(app: &mut App, stage: usize, seed: u64) -> (Tracker, u32) {
+    let world = app.world_mut();
+    world.insert_resource(GameRng::from_seed(seed));
+    world.insert_resource(Tracker::default());
+    world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::Playing);
+    app.update();
+
+    // Runs always start on the first stage, so skip ahead
+    let world = app.world_mut();
+    world.resource_mut::<GameState>().skip_to(stage);
+    world.trigger(NextStageEvent);
+
+    while app.world().resource::<Tracker>().outcome.is_none()
+        && app.world().resource::<Tracker>().tick < MAX_ATTEMPT_TICKS
+    {
+        app.update();
+    }
+
+    let score = app.world().resource::<GameState>().score;
+    let world = app.world_mut();
+    world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::MainMenu);
+    app.update();
+    let tracker = std::mem::take(&mut *app.world_mut().resource_mut::<Tracker>());
+    (tracker, score)
+}
+
+fn count_tick(mut tracker: ResMut<Tracker>) {
+    tracker.tick += 1;
+}
+
+fn on_hook(_: On<HookEvent>, mut tracker: ResMut<Tracker>) {
+    tracker.hooked += 1;
+    tracker.hooked_at = Some(tracker.tick);
+}
+
+fn on_extraction(_: On<FishExtractedEvent>, mut tracker: ResMut<Tracker>, time: Res<Time<Fixed>>) {
+    tracker.landed += 1;
+    if let Some(hooked_at) = tracker.hooked_at.take() {
+        let secs = (tracker.tick - hooked_at) as f32 * time.timestep().as_secs_f32();
+        tracker.land_times.push(secs);
+    }
+}
+
+fn on_hook_lost(_: On<HookLostEvent>, mut tracker: ResMut<Tracker>) {
+    tracker.lost += 1;
+    tracker.hooked_at = None;
+}
+
+fn on_stage_clear(_: On<StageClearEvent>, mut tracker: ResMut<Tracker>) {
+    tracker.outcome.get_or_insert(Outcome::Cleared);
+}
+
+fn on_game_over(_: On<GameOverEvent>, mut tracker: ResMut<Tracker>) {
+    tracker.outcome.get_or_insert(Outcome::Failed);
+}
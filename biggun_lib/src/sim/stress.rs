@@ -0,0 +1,189 @@
+//! Stress benchmark for crowded lakes. Bots fish a copy of the sample stage
+//! kept topped up with thousands of fish, and the time taken by each tick is
+//! reported.
+
+use super::{BotPolicy, SimSettings, build_app, percentile, wait_for_species};
+use crate::{
+    environment::{
+        fish::{self, Fish},
+        species::{Species, SpeciesLibrary},
+    },
+    game_manager::{
+        TickSet,
+        config::Config,
+        rng::GameRng,
+        session::{PlayMode, Session},
+        state::{AppState, GameState},
+    },
+    player::Player,
+};
+use bevy::prelude::*;
+use rand::Rng;
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// How to run a stress benchmark
+pub struct StressSettings {
+    /// Where `assets/` is, relative to the running crate
+    pub asset_root: String,
+    /// Number of fish kept in the lake, one benchmark for each
+    pub fish_counts: Vec<u32>,
+    /// Ticks timed for each fish count
+    pub ticks: u32,
+    pub seed: u64,
+}
+
+/// Tick times of a lake with `fish` fish, in milliseconds
+pub struct StressResult {
+    pub fish: u32,
+    /// Sorted
+    pub tick_times: Vec<f32>,
+}
+
+impl StressResult {
+    pub fn mean(&self) -> f32 {
+        self.tick_times.iter().sum::<f32>() / self.tick_times.len().max(1) as f32
+    }
+}
+
+/// Results of a whole stress benchmark
+pub struct StressReport {
+    /// Length of a `FixedUpdate` tick in milliseconds. Ticks that take longer
+    /// than this can't keep up in the game.
+    pub timestep: f32,
+    pub results: Vec<StressResult>,
+}
+
+impl fmt::Display for StressReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "timestep: {:.2} ms", self.timestep)?;
+        writeln!(
+            f,
+            "{:>6} {:>6} {:>8} {:>8} {:>8} {:>8} {:>6}",
+            "fish", "ticks", "mean ms", "p50 ms", "p99 ms", "max ms", "over"
+        )?;
+        for result in &self.results {
+            let over = result
+                .tick_times
+                .iter()
+                .filter(|&&time| time > self.timestep)
+                .count();
+            writeln!(
+                f,
+                "{:>6} {:>6} {:>8.3} {:>8.3} {:>8.3} {:>8.3} {:>5.1}%",
+                result.fish,
+                result.tick_times.len(),
+                result.mean(),
+                percentile(&result.tick_times, 0.5),
+                percentile(&result.tick_times, 0.99),
+                percentile(&result.tick_times, 1.),
+                over as f32 / result.tick_times.len().max(1) as f32 * 100.,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Times `settings.ticks` ticks of four greedy bots fishing `sample_stage`
+/// with each of `settings.fish_counts` fish in the lake. The stage can't be
+/// cleared or run out of time.
+pub fn stress(mut config: Config, settings: &StressSettings) -> Result<StressReport, String> {
+    config.stages = vec![config.sample_stage.clone()];
+    let stage = &mut config.stages[0];
+    stage.target_score = u32::MAX / Player::MAX as u32;
+    stage.time = Duration::from_secs(60 * 60 * 24);
+
+    let mut app = build_app(
+        config,
+        &SimSettings {
+            asset_root: settings.asset_root.clone(),
+            runs_per_stage: 0,
+            policy: BotPolicy::Greedy,
+            seed: settings.seed,
+        },
+    );
+    app.insert_resource(Session {
+        players: Player::MAX,
+        mode: PlayMode::Coop,
+    })
+    .add_systems(
+        FixedUpdate,
+        fill_lake
+            .in_set(TickSet::Fish)
+            .before(fish::handle_spawn)
+            .run_if(in_state(AppState::Playing)),
+    );
+    wait_for_species(&mut app)?;
+
+    let timestep = Time::<Fixed>::default().timestep().as_secs_f32() * 1000.;
+    let mut results = Vec::with_capacity(settings.fish_counts.len());
+    for &fish in &settings.fish_counts {
+        let world = app.world_mut();
+        world.resource_mut::<Config>().stages[0].max_fish = fish;
+        world.insert_resource(GameRng::from_seed(settings.seed));
+        world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::Playing);
+        // Starts the run and fills the lake, which isn't timed
+        app.update();
+        app.update();
+
+        let mut tick_times = Vec::with_capacity(settings.ticks as usize);
+        for _ in 0..settings.ticks {
+            let start = Instant::now();
+            app.update();
+            tick_times.push(start.elapsed().as_secs_f32() * 1000.);
+        }
+        tick_times.sort_by(f32::total_cmp);
+        results.push(StressResult { fish, tick_times });
+
+        app.world_mut()
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::MainMenu);
+        app.update();
+    }
+
+    Ok(StressReport { timestep, results })
+}
+
+/// Tops the lake up to the stage's `max_fish` every tick, scattering new fish
+/// across the lake instead of spawning them at its edges
+#[allow(clippy::too_many_arguments)]
+fn fill_lake(
+    mut commands: Commands,
+    config: Res<Config>,
+    mut state: ResMut<GameState>,
+    asset_server: Res<AssetServer>,
+    library: Res<SpeciesLibrary>,
+    species_assets: Res<Assets<Species>>,
+    fish_query: Query<&Fish>,
+    mut rng: ResMut<GameRng>,
+) {
+    let rng = &mut rng.spawn;
+    let stage = state.cur_stage(&config);
+    let missing = stage.max_fish.saturating_sub(state.fish_count);
+    for _ in 0..missing {
+        let Some((handle, species, depth, _)) =
+            fish::roll_species(stage, &library, &species_assets, &fish_query, rng)
+        else {
+            return;
+        };
+        let x = rng.random_range(-config.game_width..config.game_width);
+        let entity = Fish::spawn_new(
+            handle.clone(),
+            species,
+            &depth,
+            rng,
+            &mut commands,
+            &asset_server,
+            &config,
+        );
+        commands
+            .entity(entity)
+            .entry::<Transform>()
+            .and_modify(move |mut transform| transform.translation.x = x);
+        state.fish_count += 1;
+    }
+}
@@ -7,5 +7,7 @@ impl Layer {
     pub const BOAT: f32 = 1.;
     pub const WATER: f32 = 2.;
     pub const FISH: f32 = 3.;
+    /// In front of fish, so they can hide behind obstacles
+    pub const OBSTACLES: f32 = 3.5;
     pub const HOOK: f32 = 4.;
 }
@@ -4,3 +4,33 @@ use bevy::prelude::*;
 #[derive(Component)]
 #[require(Text)]
 pub struct ScoreDisplay;
+
+/// Shows the current stage's target score
+#[derive(Component)]
+#[require(Text)]
+pub struct TargetDisplay;
+
+/// Names the lure on a player's hook
+#[derive(Component)]
+#[require(Text)]
+pub struct LureDisplay;
+
+/// Fill of the HUD bar showing how close the line is to breaking
+#[derive(Component)]
+#[require(Node)]
+pub struct TensionMeter;
+
+/// HUD panel for the stage's boss, shown while it is in the lake
+#[derive(Component)]
+#[require(Node)]
+pub struct BossBar;
+
+/// Names the boss and what it is doing
+#[derive(Component)]
+#[require(Text)]
+pub struct BossName;
+
+/// Fill of the boss's stamina bar
+#[derive(Component)]
+#[require(Node)]
+pub struct BossMeter;
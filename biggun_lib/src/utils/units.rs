@@ -1,6 +1,9 @@
 //! Contains all structs for in-world units
 
-#[derive(Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(transparent)]
 pub struct Ounces(pub u32);
 
 impl Ounces {
@@ -18,7 +21,8 @@ impl Ounces {
 }
 
 /// 1 Inch corresponds to 1 world unit
-#[derive(Clone)]
+#[derive(Clone, Deserialize)]
+#[serde(transparent)]
 pub struct Inches(pub u32);
 
 impl Inches {
@@ -26,10 +30,6 @@ impl Inches {
         Inches(ft * 12 + ins)
     }
 
-    pub fn ft_ins(&self) -> (u32, u32) {
-        (self.0 / 12, self.0 % 12)
-    }
-
     pub fn lerp(&self, other: &Inches, weight: f32) -> Inches {
         Inches(self.0 + ((other.0 - self.0) as f32 * weight).round() as u32)
     }
@@ -0,0 +1,63 @@
+use biggun_lib::{
+    prelude::*,
+    sim::{
+        self, BotPolicy, SimSettings,
+        stress::{self, StressSettings},
+    },
+};
+
+/// Where the game's assets are, wherever the sim is run from
+const ASSET_ROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../biggun_game/assets");
+
+fn main() {
+    let config = match arg_value("--config") {
+        Some(path) => Config::from_file(&path).unwrap_or_else(|e| exit_with(e)),
+        None => Config::default(),
+    };
+    if let Some(counts) = arg_value("--stress") {
+        let settings = StressSettings {
+            asset_root: ASSET_ROOT.to_string(),
+            fish_counts: counts
+                .split(',')
+                .map(|count| {
+                    count
+                        .trim()
+                        .parse()
+                        .unwrap_or_else(|e| exit_with(format!("--stress {count}: {e}")))
+                })
+                .collect(),
+            ticks: parse_arg("--ticks").unwrap_or(600),
+            seed: parse_arg("--seed").unwrap_or(0),
+        };
+        let report = stress::stress(config, &settings).unwrap_or_else(|e| exit_with(e));
+        print!("{report}");
+        return;
+    }
+
+    let settings = SimSettings {
+        asset_root: ASSET_ROOT.to_string(),
+        runs_per_stage: parse_arg("--runs").unwrap_or(1000),
+        policy: parse_arg("--policy").unwrap_or(BotPolicy::Patient),
+        seed: parse_arg("--seed").unwrap_or(0),
+    };
+
+    let report = sim::simulate(config, &settings).unwrap_or_else(|e| exit_with(e));
+    print!("{report}");
+}
+
+fn exit_with(error: impl std::fmt::Display) -> ! {
+    eprintln!("{error}");
+    std::process::exit(1);
+}
+
+/// Parses the value given for `flag`, exiting if it is malformed
+fn parse_arg<T: std::str::FromStr>(flag: &str) -> Option<T>
+where
+    T::Err: std::fmt::Display,
+{
+    arg_value(flag).map(|value| {
+        value
+            .parse()
+            .unwrap_or_else(|e| exit_with(format!("{flag} {value}: {e}")))
+    })
+}